@@ -1,14 +1,24 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
 
-use aoc_lib::{Bench, BenchError, BenchResult, Day};
-use color_eyre::eyre::{eyre, Result};
+use aoc_lib::{Bench, BenchError, BenchResult, Day, NoError};
+use color_eyre::{
+    eyre::{eyre, Result},
+    Report,
+};
 
 pub const DAY: Day = Day {
     day: 1,
     name: "No Time for a Taxicab",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[
+        ("Part 2 Walked", run_part2_walked),
+        ("Render", run_render),
+        ("Revisits", run_revisits),
+    ],
 };
 
 fn run_part1(input: &str, b: Bench) -> BenchResult {
@@ -17,14 +27,52 @@ fn run_part1(input: &str, b: Bench) -> BenchResult {
 }
 
 fn run_part2(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    b.bench(|| part2_segments(&instructions))
+}
+
+fn run_part2_walked(input: &str, b: Bench) -> BenchResult {
     let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
     b.bench(|| part2(&instructions))
 }
 
+fn run_render(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let route = Route::new(&instructions).map_err(|e| BenchError::UserError(e.into()))?;
+
+    // The SVG is only written out if AOC_1601_SVG gives a path for it.
+    if let Some(path) = std::env::var_os("AOC_1601_SVG") {
+        std::fs::write(path, route.render_svg()).map_err(|e| BenchError::UserError(e.into()))?;
+    }
+
+    b.bench_alt(|| Ok::<_, NoError>(route.render_ascii(80, 40)))
+}
+
+fn run_revisits(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let segments: Vec<_> = Walker::new(&instructions)
+        .collect::<Result<_>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench_alt(|| {
+        let mut buf = String::new();
+        for revisit in revisits(&segments) {
+            writeln!(
+                &mut buf,
+                "({}, {}): {} visits, by instructions {:?}",
+                revisit.pos.0, revisit.pos.1, revisit.visits, revisit.visited_by
+            )
+            .unwrap();
+        }
+
+        Ok::<_, NoError>(buf)
+    })
+}
+
 #[derive(Debug, Copy, Clone)]
 enum Instruction {
-    Left(i16),
-    Right(i16),
+    Left(i64),
+    Right(i64),
 }
 
 impl Instruction {
@@ -44,7 +92,7 @@ impl Instruction {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Heading {
     North,
     East,
@@ -72,100 +120,561 @@ impl Heading {
             West => North,
         }
     }
+
+    fn step(self) -> (i64, i64) {
+        use Heading::*;
+        match self {
+            North => (0, 1),
+            East => (1, 0),
+            South => (0, -1),
+            West => (-1, 0),
+        }
+    }
 }
 
-fn part1(instrs: &[Instruction]) -> Result<i16> {
-    let (_, (x, y)) =
-        instrs
-            .iter()
-            .fold((Heading::North, (0, 0)), |(heading, (x, y)), next| {
-                match (heading, *next) {
-                    (Heading::North, Instruction::Left(distance)) => {
-                        (heading.turn_left(), (x - distance, y))
-                    }
-                    (Heading::North, Instruction::Right(distance)) => {
-                        (heading.turn_right(), (x + distance, y))
-                    }
-                    (Heading::East, Instruction::Left(distance)) => {
-                        (heading.turn_left(), (x, y + distance))
-                    }
-                    (Heading::East, Instruction::Right(distance)) => {
-                        (heading.turn_right(), (x, y - distance))
-                    }
-                    (Heading::South, Instruction::Left(distance)) => {
-                        (heading.turn_left(), (x + distance, y))
-                    }
-                    (Heading::South, Instruction::Right(distance)) => {
-                        (heading.turn_right(), (x - distance, y))
-                    }
-                    (Heading::West, Instruction::Left(distance)) => {
-                        (heading.turn_left(), (x, y - distance))
-                    }
-                    (Heading::West, Instruction::Right(distance)) => {
-                        (heading.turn_right(), (x, y + distance))
-                    }
-                }
-            });
+fn overflow_error(instr_idx: usize) -> Report {
+    eyre!("Position overflows after instruction {}", instr_idx + 1)
+}
+
+fn offset((x, y): (i64, i64), (dir_x, dir_y): (i64, i64), distance: i64) -> Option<(i64, i64)> {
+    let x = x.checked_add(dir_x.checked_mul(distance)?)?;
+    let y = y.checked_add(dir_y.checked_mul(distance)?)?;
+    Some((x, y))
+}
+
+fn taxicab_distance((x, y): (i64, i64)) -> Result<i64> {
+    x.checked_abs()
+        .zip(y.checked_abs())
+        .and_then(|(x, y)| x.checked_add(y))
+        .ok_or_else(|| eyre!("Distance to ({}, {}) overflows", x, y))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Segment {
+    start: (i64, i64),
+    end: (i64, i64),
+    heading: Heading,
+}
+
+impl Segment {
+    // The end point isn't included, as it's the start of the next segment.
+    fn points(self) -> impl Iterator<Item = (i64, i64)> {
+        let delta_x = self.end.0.cmp(&self.start.0) as i64;
+        let delta_y = self.end.1.cmp(&self.start.1) as i64;
+
+        std::iter::successors(Some(self.start), move |&(x, y)| {
+            Some((x + delta_x, y + delta_y))
+        })
+        .take_while(move |&pos| pos != self.end)
+    }
+}
+
+struct Walker<'a> {
+    instrs: std::iter::Enumerate<std::slice::Iter<'a, Instruction>>,
+    heading: Heading,
+    pos: (i64, i64),
+    overflowed: bool,
+}
 
-    Ok(x.abs() + y.abs())
+impl<'a> Walker<'a> {
+    fn new(instrs: &'a [Instruction]) -> Self {
+        Self {
+            instrs: instrs.iter().enumerate(),
+            heading: Heading::North,
+            pos: (0, 0),
+            overflowed: false,
+        }
+    }
 }
 
-fn part2(instrs: &[Instruction]) -> Result<i16> {
-    let positions = instrs.iter().scan((Heading::North, (0, 0)), |state, next| {
-        let (heading, (x, y)) = *state;
+impl Iterator for Walker<'_> {
+    type Item = Result<Segment>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.overflowed {
+            return None;
+        }
+
+        let (i, instr) = self.instrs.next()?;
+        let (heading, distance) = match *instr {
+            Instruction::Left(distance) => (self.heading.turn_left(), distance),
+            Instruction::Right(distance) => (self.heading.turn_right(), distance),
+        };
 
-        let (next_heading, next_coords) = match (heading, *next) {
-            (Heading::North, Instruction::Left(distance)) => {
-                (heading.turn_left(), (x - distance, y))
+        let end = match offset(self.pos, heading.step(), distance) {
+            Some(end) => end,
+            None => {
+                self.overflowed = true;
+                return Some(Err(overflow_error(i)));
             }
-            (Heading::North, Instruction::Right(distance)) => {
-                (heading.turn_right(), (x + distance, y))
+        };
+
+        let segment = Segment {
+            start: self.pos,
+            end,
+            heading,
+        };
+        self.heading = heading;
+        self.pos = end;
+
+        Some(Ok(segment))
+    }
+}
+
+fn part1(instrs: &[Instruction]) -> Result<i64> {
+    let mut end = (0, 0);
+    for segment in Walker::new(instrs) {
+        end = segment?.end;
+    }
+
+    taxicab_distance(end)
+}
+
+// Walking every point needs memory proportional to the route length, so give up on routes
+// that `part2_segments` should be used for instead.
+const MAX_WALKED_POINTS: u64 = 1_000_000;
+
+fn part2(instrs: &[Instruction]) -> Result<i64> {
+    let mut visited = HashSet::new();
+    let mut walked = 0_u64;
+
+    for (i, segment) in Walker::new(instrs).enumerate() {
+        let segment = segment?;
+        let length =
+            segment.start.0.abs_diff(segment.end.0) + segment.start.1.abs_diff(segment.end.1);
+        walked = walked.saturating_add(length);
+        if walked > MAX_WALKED_POINTS {
+            return Err(eyre!(
+                "Route walks more than {} points by instruction {}",
+                MAX_WALKED_POINTS,
+                i + 1
+            ));
+        }
+
+        for pos in segment.points() {
+            if !visited.insert(pos) {
+                return taxicab_distance(pos);
             }
-            (Heading::East, Instruction::Left(distance)) => {
-                (heading.turn_left(), (x, y + distance))
+        }
+    }
+
+    Err(eyre!("No coordinates visited twice"))
+}
+
+fn part2_segments(instrs: &[Instruction]) -> Result<i64> {
+    // Segments are keyed by their fixed coordinate, and store the closed range of points
+    // actually visited. The end point is left off, as it's the start of the next segment.
+    let mut horizontal: BTreeMap<i64, Vec<(i64, i64)>> = BTreeMap::new();
+    let mut vertical: BTreeMap<i64, Vec<(i64, i64)>> = BTreeMap::new();
+
+    for segment in Walker::new(instrs) {
+        let Segment {
+            start: (x, y),
+            end: (end_x, end_y),
+            ..
+        } = segment?;
+        if (end_x, end_y) == (x, y) {
+            continue;
+        }
+
+        let delta_x = end_x.cmp(&x) as i64;
+        let delta_y = end_y.cmp(&y) as i64;
+        let (last_x, last_y) = (end_x - delta_x, end_y - delta_y);
+
+        let mut first_crossing = None;
+        let mut check = |px: i64, py: i64| {
+            let along = (px - x).abs() + (py - y).abs();
+            match first_crossing {
+                Some((prev, _)) if prev <= along => {}
+                _ => first_crossing = Some((along, (px, py))),
             }
-            (Heading::East, Instruction::Right(distance)) => {
-                (heading.turn_right(), (x, y - distance))
+        };
+
+        if delta_x == 0 {
+            let (lo, hi) = (y.min(last_y), y.max(last_y));
+
+            for (&seg_y, segs) in horizontal.range(lo..=hi) {
+                for &(seg_lo, seg_hi) in segs {
+                    if (seg_lo..=seg_hi).contains(&x) {
+                        check(x, seg_y);
+                    }
+                }
             }
-            (Heading::South, Instruction::Left(distance)) => {
-                (heading.turn_left(), (x + distance, y))
+
+            for &(seg_lo, seg_hi) in vertical.get(&x).into_iter().flatten() {
+                let (overlap_lo, overlap_hi) = (lo.max(seg_lo), hi.min(seg_hi));
+                if overlap_lo <= overlap_hi {
+                    check(x, if delta_y > 0 { overlap_lo } else { overlap_hi });
+                }
             }
-            (Heading::South, Instruction::Right(distance)) => {
-                (heading.turn_right(), (x - distance, y))
+
+            vertical.entry(x).or_default().push((lo, hi));
+        } else {
+            let (lo, hi) = (x.min(last_x), x.max(last_x));
+
+            for (&seg_x, segs) in vertical.range(lo..=hi) {
+                for &(seg_lo, seg_hi) in segs {
+                    if (seg_lo..=seg_hi).contains(&y) {
+                        check(seg_x, y);
+                    }
+                }
             }
-            (Heading::West, Instruction::Left(distance)) => {
-                (heading.turn_left(), (x, y - distance))
+
+            for &(seg_lo, seg_hi) in horizontal.get(&y).into_iter().flatten() {
+                let (overlap_lo, overlap_hi) = (lo.max(seg_lo), hi.min(seg_hi));
+                if overlap_lo <= overlap_hi {
+                    check(if delta_x > 0 { overlap_lo } else { overlap_hi }, y);
+                }
             }
-            (Heading::West, Instruction::Right(distance)) => {
-                (heading.turn_right(), (x, y + distance))
+
+            horizontal.entry(y).or_default().push((lo, hi));
+        }
+
+        if let Some((_, pos)) = first_crossing {
+            return taxicab_distance(pos);
+        }
+    }
+
+    Err(eyre!("No coordinates visited twice"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Revisit {
+    pos: (i64, i64),
+    visits: usize,
+    // Index of the instruction responsible for each visit, including the first.
+    visited_by: Vec<usize>,
+}
+
+// Unlike part 2, this also counts the final position as visited. Crossings are found with the
+// same interval maps as `part2_segments`, so long legs aren't walked point by point.
+fn revisits(segments: &[Segment]) -> Vec<Revisit> {
+    // Closed ranges of visited points and the instruction that visited them, keyed by their
+    // fixed coordinate.
+    let mut horizontal: BTreeMap<i64, Vec<(i64, i64, usize)>> = BTreeMap::new();
+    let mut vertical: BTreeMap<i64, Vec<(i64, i64, usize)>> = BTreeMap::new();
+
+    let ranges = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.start != s.end)
+        .map(|(i, s)| {
+            let delta_x = s.end.0.cmp(&s.start.0) as i64;
+            let delta_y = s.end.1.cmp(&s.start.1) as i64;
+            (i, s.start, (s.end.0 - delta_x, s.end.1 - delta_y))
+        })
+        .chain(segments.last().map(|s| (segments.len() - 1, s.end, s.end)));
+
+    let mut revisit_idxs = HashMap::new();
+    let mut revisits = Vec::new();
+    let mut crossings = Vec::new();
+
+    for (instr_idx, (x, y), (last_x, last_y)) in ranges {
+        crossings.clear();
+
+        if x == last_x {
+            let (lo, hi) = (y.min(last_y), y.max(last_y));
+
+            for (&seg_y, segs) in horizontal.range(lo..=hi) {
+                for &(seg_lo, seg_hi, prev_idx) in segs {
+                    if (seg_lo..=seg_hi).contains(&x) {
+                        crossings.push((seg_y.abs_diff(y), (x, seg_y), prev_idx));
+                    }
+                }
             }
-        };
 
-        *state = (next_heading, next_coords);
-        Some(((x, y), next_coords))
-    });
+            for &(seg_lo, seg_hi, prev_idx) in vertical.get(&x).into_iter().flatten() {
+                for py in lo.max(seg_lo)..=hi.min(seg_hi) {
+                    crossings.push((py.abs_diff(y), (x, py), prev_idx));
+                }
+            }
 
-    let mut visited = HashSet::new();
+            vertical.entry(x).or_default().push((lo, hi, instr_idx));
+        } else {
+            let (lo, hi) = (x.min(last_x), x.max(last_x));
 
-    for ((mut start_x, mut start_y), (end_x, end_y)) in positions {
-        let delta_y = end_y.cmp(&start_y) as i16;
-        let delta_x = end_x.cmp(&start_x) as i16;
+            for (&seg_x, segs) in vertical.range(lo..=hi) {
+                for &(seg_lo, seg_hi, prev_idx) in segs {
+                    if (seg_lo..=seg_hi).contains(&y) {
+                        crossings.push((seg_x.abs_diff(x), (seg_x, y), prev_idx));
+                    }
+                }
+            }
 
-        while start_x != end_x || start_y != end_y {
-            if !visited.insert((start_x, start_y)) {
-                return Ok(start_x.abs() + start_y.abs());
+            for &(seg_lo, seg_hi, prev_idx) in horizontal.get(&y).into_iter().flatten() {
+                for px in lo.max(seg_lo)..=hi.min(seg_hi) {
+                    crossings.push((px.abs_diff(x), (px, y), prev_idx));
+                }
             }
 
-            start_x += delta_x;
-            start_y += delta_y;
+            horizontal.entry(y).or_default().push((lo, hi, instr_idx));
+        }
+
+        // In the order they're walked over. A point crossed by several earlier instructions is
+        // already a revisit, so which of them is kept doesn't matter.
+        crossings.sort_unstable();
+        crossings.dedup_by_key(|&mut (_, pos, _)| pos);
+
+        for &(_, pos, prev_idx) in &crossings {
+            match revisit_idxs.get(&pos) {
+                Some(&idx) => {
+                    let revisit: &mut Revisit = &mut revisits[idx];
+                    revisit.visits += 1;
+                    revisit.visited_by.push(instr_idx);
+                }
+                None => {
+                    revisit_idxs.insert(pos, revisits.len());
+                    revisits.push(Revisit {
+                        pos,
+                        visits: 2,
+                        visited_by: vec![prev_idx, instr_idx],
+                    });
+                }
+            }
         }
     }
-    Err(eyre!("No coordinates visited twice"))
+
+    revisits
+}
+
+struct Route {
+    segments: Vec<Segment>,
+    end: (i64, i64),
+    revisits: Vec<Revisit>,
+}
+
+impl Route {
+    fn new(instrs: &[Instruction]) -> Result<Self> {
+        let segments: Vec<_> = Walker::new(instrs).collect::<Result<_>>()?;
+        let end = segments.last().map_or((0, 0), |s| s.end);
+        let revisits = revisits(&segments);
+
+        Ok(Self {
+            segments,
+            end,
+            revisits,
+        })
+    }
+
+    fn bounds(&self) -> ((i64, i64), (i64, i64)) {
+        self.segments.iter().map(|s| s.end).fold(
+            ((0, 0), (0, 0)),
+            |((min_x, min_y), (max_x, max_y)), (x, y)| {
+                ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+            },
+        )
+    }
+
+    fn render_ascii(&self, max_width: usize, max_height: usize) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+
+        // Widen to avoid overflowing on routes that cover most of the i64 range.
+        let span_x = (max_x as i128 - min_x as i128) + 1;
+        let span_y = (max_y as i128 - min_y as i128) + 1;
+        let scale = [
+            1,
+            (span_x + max_width as i128 - 1) / max_width as i128,
+            (span_y + max_height as i128 - 1) / max_height as i128,
+        ]
+        .into_iter()
+        .max()
+        .unwrap();
+
+        let width = ((span_x + scale - 1) / scale) as usize;
+        let height = ((span_y + scale - 1) / scale) as usize;
+        let cell = |(x, y): (i64, i64)| {
+            let col = (x as i128 - min_x as i128) / scale;
+            let row = (max_y as i128 - y as i128) / scale;
+            (col as usize, row as usize)
+        };
+
+        let mut grid = vec![b' '; width * height];
+
+        for segment in &self.segments {
+            let (start_col, start_row) = cell(segment.start);
+            let (end_col, end_row) = cell(segment.end);
+
+            if segment.start.1 == segment.end.1 {
+                for col in start_col.min(end_col)..=start_col.max(end_col) {
+                    let c = &mut grid[start_row * width + col];
+                    *c = if matches!(*c, b' ' | b'-') {
+                        b'-'
+                    } else {
+                        b'+'
+                    };
+                }
+            } else {
+                for row in start_row.min(end_row)..=start_row.max(end_row) {
+                    let c = &mut grid[row * width + start_col];
+                    *c = if matches!(*c, b' ' | b'|') {
+                        b'|'
+                    } else {
+                        b'+'
+                    };
+                }
+            }
+        }
+
+        for revisit in &self.revisits {
+            let (col, row) = cell(revisit.pos);
+            grid[row * width + col] = b'*';
+        }
+
+        let (col, row) = cell((0, 0));
+        grid[row * width + col] = b'O';
+        let (col, row) = cell(self.end);
+        grid[row * width + col] = b'E';
+
+        let mut buf = String::with_capacity((width + 1) * height);
+        for row in grid.chunks_exact(width) {
+            buf.extend(row.iter().map(|&c| c as char));
+            buf.push('\n');
+        }
+
+        buf
+    }
+
+    fn render_svg(&self) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        let span = (max_x as f64 - min_x as f64).max(max_y as f64 - min_y as f64);
+        let stroke = (span / 100.0).max(0.1);
+        let radius = stroke * 3.0;
+
+        // SVG's Y axis points down, so north is flipped to be up. Widened as in `render_ascii`,
+        // since neither the span nor the flip fits in an i64 for every route.
+        let (min_x, min_y) = (min_x as i128, min_y as i128);
+        let (max_x, max_y) = (max_x as i128, max_y as i128);
+        let mut buf = String::new();
+        writeln!(
+            &mut buf,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+            min_x - 1,
+            -max_y - 1,
+            max_x - min_x + 2,
+            max_y - min_y + 2,
+        )
+        .unwrap();
+
+        buf.push_str(r#"  <polyline points="0,0"#);
+        for segment in &self.segments {
+            write!(&mut buf, " {},{}", segment.end.0, -(segment.end.1 as i128)).unwrap();
+        }
+        writeln!(
+            &mut buf,
+            r#"" fill="none" stroke="black" stroke-width="{:.2}"/>"#,
+            stroke
+        )
+        .unwrap();
+
+        let markers = self
+            .revisits
+            .iter()
+            .map(|r| (r.pos, "blue"))
+            .chain([((0, 0), "green"), (self.end, "red")]);
+        for ((x, y), colour) in markers {
+            writeln!(
+                &mut buf,
+                r#"  <circle cx="{}" cy="{}" r="{:.2}" fill="{}"/>"#,
+                x,
+                -(y as i128),
+                radius,
+                colour
+            )
+            .unwrap();
+        }
+
+        buf.push_str("</svg>\n");
+        buf
+    }
 }
 
 #[cfg(test)]
 mod tests_1601 {
     use super::*;
+    use crate::rng::Rng;
+
+    fn distance(rng: &mut Rng) -> i64 {
+        1 + rng.below(9) as i64
+    }
+
+    struct GeneratedInput {
+        input: String,
+        part1: i64,
+        part2: Option<i64>,
+    }
+
+    // With no revisit, the route is a staircase that only ever heads away from the origin.
+    // Otherwise, the first four instructions loop back to cross the first leg at the requested
+    // distance, then the rest are a staircase heading away from everything walked so far.
+    fn generate_input(seed: u64, len: usize, first_revisit: Option<i64>) -> Result<GeneratedInput> {
+        let mut rng = Rng::new(seed);
+        let mirror = rng.below(2) == 0;
+        let (fwd, back) = if mirror { ('L', 'R') } else { ('R', 'L') };
+
+        let mut instrs = Vec::with_capacity(len);
+        let (mut x, mut y) = (0, 0);
+
+        match first_revisit {
+            None => {
+                if len == 0 {
+                    return Err(eyre!("Need at least one instruction"));
+                }
+
+                for i in 0..len {
+                    let distance = distance(&mut rng);
+                    if i % 2 == 0 {
+                        instrs.push((fwd, distance));
+                        x += distance;
+                    } else {
+                        instrs.push((back, distance));
+                        y += distance;
+                    }
+                }
+            }
+            Some(revisit) => {
+                if len < 4 {
+                    return Err(eyre!("Need at least 4 instructions to revisit a point"));
+                }
+                if revisit < 0 {
+                    return Err(eyre!("Invalid revisit distance: {}", revisit));
+                }
+
+                let (overshoot, height, drop) =
+                    (distance(&mut rng), distance(&mut rng), distance(&mut rng));
+                instrs.extend([
+                    (fwd, revisit + overshoot),
+                    (back, height),
+                    (back, overshoot),
+                    (back, height + drop),
+                ]);
+                x = revisit;
+                y = -drop;
+
+                for i in 0..len - 4 {
+                    let distance = distance(&mut rng);
+                    if i % 2 == 0 {
+                        instrs.push((back, distance));
+                        x += distance;
+                    } else {
+                        instrs.push((fwd, distance));
+                        y -= distance;
+                    }
+                }
+            }
+        }
+
+        let input = instrs
+            .iter()
+            .map(|(dir, distance)| format!("{}{}", dir, distance))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(GeneratedInput {
+            input,
+            part1: x.abs() + y.abs(),
+            part2: first_revisit,
+        })
+    }
 
     #[test]
     fn part1_example() {
@@ -186,4 +695,255 @@ mod tests_1601 {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn part2_segments_matches_part2() {
+        let tests = [
+            "R8, R4, R4, R8",
+            "R2, R2, R2, R2, R1",
+            "R5, R1, R1, R5, L3, L3",
+            "L3, R4, L1, L1, L10, R2, R2, L7",
+            "R1, L1, L1, L1, L2",
+        ];
+
+        for &test in &tests {
+            let instructions = Instruction::parse(test).unwrap();
+            let expected = part2(&instructions).unwrap();
+            let actual = part2_segments(&instructions).unwrap();
+            assert_eq!(actual, expected, "{}", test);
+        }
+
+        let instructions = Instruction::parse("R2, L3").unwrap();
+        assert!(part2_segments(&instructions).is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        let instructions = Instruction::parse("R9223372036854775807, L1, R1").unwrap();
+        let err = part1(&instructions).unwrap_err();
+        assert_eq!(err.to_string(), "Position overflows after instruction 3");
+        assert!(part2_segments(&instructions).is_err());
+        let err = part2(&instructions).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Route walks more than 1000000 points by instruction 1"
+        );
+
+        let instructions = Instruction::parse("R20000000").unwrap();
+        assert!(part2(&instructions).is_err());
+        assert!(part2_segments(&instructions).is_err());
+
+        let instructions = Instruction::parse("L9223372036854775807, L1, R1").unwrap();
+        assert!(part1(&instructions).is_err());
+
+        assert!(Instruction::parse("R9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn walker() {
+        let instructions = Instruction::parse("R2, L3, L0").unwrap();
+        let expected = [
+            Segment {
+                start: (0, 0),
+                end: (2, 0),
+                heading: Heading::East,
+            },
+            Segment {
+                start: (2, 0),
+                end: (2, 3),
+                heading: Heading::North,
+            },
+            Segment {
+                start: (2, 3),
+                end: (2, 3),
+                heading: Heading::West,
+            },
+        ];
+
+        let actual: Vec<_> = Walker::new(&instructions).collect::<Result<_>>().unwrap();
+        assert_eq!(actual, expected);
+
+        let points: Vec<_> = actual[1].points().collect();
+        assert_eq!(points, [(2, 0), (2, 1), (2, 2)]);
+        assert_eq!(actual[2].points().count(), 0);
+    }
+
+    #[test]
+    fn render_ascii() {
+        let instructions = Instruction::parse("R8, R4, R4, R8").unwrap();
+        let route = Route::new(&instructions).unwrap();
+
+        let actual = route.render_ascii(80, 40);
+        let expected = concat!(
+            "    E    \n",
+            "    |    \n",
+            "    |    \n",
+            "    |    \n",
+            "O---*---+\n",
+            "    |   |\n",
+            "    |   |\n",
+            "    |   |\n",
+            "    +---+\n",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn render_svg() {
+        let instructions = Instruction::parse("R8, R4, R4, R8").unwrap();
+        let route = Route::new(&instructions).unwrap();
+
+        let actual = route.render_svg();
+        let expected = concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -5 10 10">"#,
+            "\n",
+            r#"  <polyline points="0,0 8,0 8,4 4,4 4,-4" fill="none" stroke="black" stroke-width="0.10"/>"#,
+            "\n",
+            r#"  <circle cx="4" cy="0" r="0.30" fill="blue"/>"#,
+            "\n",
+            r#"  <circle cx="0" cy="0" r="0.30" fill="green"/>"#,
+            "\n",
+            r#"  <circle cx="4" cy="-4" r="0.30" fill="red"/>"#,
+            "\n",
+            "</svg>\n",
+        );
+
+        assert_eq!(actual, expected);
+
+        let instructions = Instruction::parse("R9223372036854775807, L1").unwrap();
+        let route = Route::new(&instructions).unwrap();
+        let actual = route.render_svg();
+        assert!(
+            actual.starts_with(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -2 9223372036854775809 3">"#
+            ),
+            "{}",
+            actual
+        );
+        assert!(actual.contains(r#"points="0,0 9223372036854775807,0 9223372036854775807,-1""#));
+
+        let instructions =
+            Instruction::parse("L9223372036854775807, L9223372036854775807, L1, R1").unwrap();
+        let actual = Route::new(&instructions).unwrap().render_svg();
+        assert!(
+            actual.contains(
+                r#"viewBox="-9223372036854775808 -1 9223372036854775809 9223372036854775810""#
+            ),
+            "{}",
+            actual
+        );
+        assert!(
+            actual.contains(r#"<circle cx="-9223372036854775806" cy="9223372036854775808" r="#),
+            "{}",
+            actual
+        );
+    }
+
+    #[test]
+    fn revisits_test() {
+        let instructions = Instruction::parse("R8, R4, R4, R8, R2, R4, R3").unwrap();
+        let segments: Vec<_> = Walker::new(&instructions).collect::<Result<_>>().unwrap();
+
+        let expected = [
+            Revisit {
+                pos: (4, 0),
+                visits: 3,
+                visited_by: vec![0, 3, 6],
+            },
+            Revisit {
+                pos: (6, 0),
+                visits: 2,
+                visited_by: vec![0, 6],
+            },
+            Revisit {
+                pos: (5, 0),
+                visits: 2,
+                visited_by: vec![0, 6],
+            },
+            Revisit {
+                pos: (3, 0),
+                visits: 2,
+                visited_by: vec![0, 6],
+            },
+        ];
+        let actual = revisits(&segments);
+
+        assert_eq!(actual, expected);
+
+        // Long legs must not be walked point by point.
+        let instructions = Instruction::parse("R9223372036854775807, L1, L1, L1").unwrap();
+        let segments: Vec<_> = Walker::new(&instructions).collect::<Result<_>>().unwrap();
+        let expected = [Revisit {
+            pos: (9223372036854775806, 0),
+            visits: 2,
+            visited_by: vec![0, 3],
+        }];
+        assert_eq!(revisits(&segments), expected);
+    }
+
+    // Visits every point in turn, to check `revisits` against.
+    fn walked_revisits(segments: &[Segment]) -> Vec<Revisit> {
+        let end = segments.last().map(|s| (s.end, segments.len() - 1));
+        let positions = segments
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s)| s.points().map(move |pos| (pos, i)))
+            .chain(end);
+
+        let mut visits: HashMap<_, Vec<_>> = HashMap::new();
+        let mut order = Vec::new();
+        for (pos, instr_idx) in positions {
+            let visited_by = visits.entry(pos).or_default();
+            visited_by.push(instr_idx);
+            if visited_by.len() == 2 {
+                order.push(pos);
+            }
+        }
+
+        order
+            .into_iter()
+            .map(|pos| Revisit {
+                pos,
+                visits: visits[&pos].len(),
+                visited_by: visits[&pos].clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generated_inputs() {
+        let cases = [
+            (1, 1, None),
+            (2, 500, None),
+            (3, 4, Some(0)),
+            (4, 4, Some(7)),
+            (5, 10_000, Some(0)),
+            (6, 10_000, Some(12_345)),
+        ];
+
+        for (seed, len, revisit) in cases {
+            let generated = generate_input(seed, len, revisit).unwrap();
+            let instructions = Instruction::parse(&generated.input).unwrap();
+            assert_eq!(instructions.len(), len, "{}", seed);
+
+            assert_eq!(part1(&instructions).unwrap(), generated.part1, "{}", seed);
+            assert_eq!(part2(&instructions).ok(), generated.part2, "{}", seed);
+            assert_eq!(
+                part2_segments(&instructions).ok(),
+                generated.part2,
+                "{}",
+                seed
+            );
+
+            let segments: Vec<_> = Walker::new(&instructions).collect::<Result<_>>().unwrap();
+            assert_eq!(revisits(&segments), walked_revisits(&segments), "{}", seed);
+        }
+
+        assert_eq!(
+            generate_input(7, 5, Some(3)).unwrap().input,
+            generate_input(7, 5, Some(3)).unwrap().input
+        );
+        assert!(generate_input(8, 3, Some(3)).is_err());
+    }
 }
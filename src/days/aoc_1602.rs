@@ -1,4 +1,11 @@
-use aoc_lib::{Bench, BenchError, BenchResult, Day};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    io::{IsTerminal, Write as _},
+    time::Duration,
+};
+
+use aoc_lib::{Bench, BenchError, BenchResult, Day, NoError};
 use color_eyre::eyre::{eyre, Result};
 
 pub const DAY: Day = Day {
@@ -6,20 +13,130 @@ pub const DAY: Day = Day {
     name: "Bathroom Security",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[
+        ("Part 2 Table", run_part2_table),
+        ("Part 2 Composed", run_part2_composed),
+        ("Synthesise", run_synthesise),
+        ("Part 2 Wrap", run_part2_wrap),
+        ("Part 2 Block", run_part2_block),
+        ("Part 2 Teleport", run_part2_teleport),
+        ("Trace", run_trace),
+    ],
 };
 
 fn run_part1(input: &str, b: Bench) -> BenchResult {
     let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
-    b.bench(|| part1(&instructions))
+    let keypad = Keypad::parse(PART1_LAYOUT, "5").map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| keypad.solve(&instructions))
 }
 
 fn run_part2(input: &str, b: Bench) -> BenchResult {
     let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
-    b.bench(|| part2(&instructions))
+    let keypad = Keypad::parse(PART2_LAYOUT, "5").map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| keypad.solve(&instructions))
 }
 
-#[derive(Debug)]
+fn run_part2_table(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let table = Keypad::parse(PART2_LAYOUT, "5")
+        .and_then(|k| TransitionTable::new(&k))
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| Ok::<_, NoError>(table.solve(&instructions)))
+}
+
+fn run_part2_composed(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let table = Keypad::parse(PART2_LAYOUT, "5")
+        .and_then(|k| TransitionTable::new(&k))
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| Ok::<_, NoError>(table.solve_composed(&instructions)))
+}
+
+fn run_synthesise(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let keypad = Keypad::parse(PART2_LAYOUT, "5").map_err(|e| BenchError::UserError(e.into()))?;
+    let code = keypad
+        .solve_labels(&instructions)
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench_alt(|| {
+        let lines = keypad.synthesise(&code)?;
+        let mut buf = String::new();
+        for line in lines {
+            buf.extend(line.into_iter().map(Instruction::as_char));
+            buf.push('\n');
+        }
+
+        Ok::<_, color_eyre::Report>(buf)
+    })
+}
+
+fn run_part2_wrap(input: &str, b: Bench) -> BenchResult {
+    run_part2_edge(input, b, Edge::Wrap)
+}
+
+fn run_part2_block(input: &str, b: Bench) -> BenchResult {
+    run_part2_edge(input, b, Edge::Block)
+}
+
+fn run_part2_teleport(input: &str, b: Bench) -> BenchResult {
+    run_part2_edge(input, b, Edge::Teleport("5".to_owned()))
+}
+
+fn run_part2_edge(input: &str, b: Bench, edge: Edge) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let keypad = Keypad::parse(PART2_LAYOUT, "5")
+        .and_then(|k| k.with_edge(edge))
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| keypad.solve(&instructions))
+}
+
+fn run_trace(input: &str, b: Bench) -> BenchResult {
+    let instructions = Instruction::parse(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let keypad = Keypad::parse(PART2_LAYOUT, "5").map_err(|e| BenchError::UserError(e.into()))?;
+    let trace = keypad.trace(&instructions);
+
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        let frames = keypad.trace_frames(&trace);
+        let delay = frame_delay(frames.len());
+        for frame in frames {
+            // Clear the screen and move the cursor to the top-left before each frame.
+            print!("\x1b[2J\x1b[H{}", frame);
+            stdout
+                .flush()
+                .map_err(|e| BenchError::UserError(e.into()))?;
+            std::thread::sleep(delay);
+        }
+    }
+
+    b.bench_alt(|| {
+        let mut buf = String::new();
+        for line in &trace {
+            keypad.render_line_trace(&mut buf, line);
+            buf.push('\n');
+        }
+
+        Ok::<_, NoError>(buf)
+    })
+}
+
+// Short traces play at a watchable speed, while long ones are sped up to fit the time limit
+// rather than holding up the benchmark for minutes.
+fn frame_delay(num_frames: usize) -> Duration {
+    const MAX_FRAME_DELAY: Duration = Duration::from_millis(100);
+    const MAX_ANIMATION: Duration = Duration::from_secs(10);
+
+    let num_frames = u32::try_from(num_frames).unwrap_or(u32::MAX).max(1);
+    (MAX_ANIMATION / num_frames).min(MAX_FRAME_DELAY)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Instruction {
     Up,
     Down,
@@ -28,6 +145,32 @@ enum Instruction {
 }
 
 impl Instruction {
+    const ALL: [Instruction; 4] = [
+        Instruction::Up,
+        Instruction::Down,
+        Instruction::Left,
+        Instruction::Right,
+    ];
+
+    // Wrapping takes us out of bounds, which is treated as a hole.
+    fn apply(self, (x, y): (usize, usize)) -> (usize, usize) {
+        match self {
+            Instruction::Up => (x, y.wrapping_sub(1)),
+            Instruction::Down => (x, y + 1),
+            Instruction::Left => (x.wrapping_sub(1), y),
+            Instruction::Right => (x + 1, y),
+        }
+    }
+
+    fn as_char(self) -> char {
+        match self {
+            Instruction::Up => 'U',
+            Instruction::Down => 'D',
+            Instruction::Left => 'L',
+            Instruction::Right => 'R',
+        }
+    }
+
     fn parse(input: &str) -> Result<Vec<Vec<Instruction>>> {
         let mut digits = Vec::new();
 
@@ -49,79 +192,434 @@ impl Instruction {
     }
 }
 
-fn part1(instrs: &[Vec<Instruction>]) -> Result<u32> {
-    let mut number = 0;
+const PART1_LAYOUT: &str = "123
+456
+789";
 
-    let mut x: u32 = 1;
-    let mut y: u32 = 1;
+const PART2_LAYOUT: &str = "  1
+ 234
+56789
+ ABC
+  D";
 
-    for digit_instrs in instrs {
-        for instr in digit_instrs {
-            match instr {
-                Instruction::Up => y = y.saturating_sub(1),
-                Instruction::Down => y = (y + 1).min(2),
-                Instruction::Left => x = x.saturating_sub(1),
-                Instruction::Right => x = (x + 1).min(2),
+// What happens when a move would leave the keypad, either off the grid or into a hole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Edge {
+    Clamp,
+    Wrap,
+    Block,
+    Teleport(String),
+}
+
+#[derive(Debug)]
+struct Keypad {
+    width: usize,
+    height: usize,
+    keys: Vec<Option<String>>,
+    start: (usize, usize),
+    edge: Edge,
+}
+
+impl Keypad {
+    fn parse(layout: &str, start_key: &str) -> Result<Keypad> {
+        Keypad::parse_cells(layout, 1, start_key)
+    }
+
+    // Each key takes up `cell_width` characters, so keys can have longer labels. Cells that
+    // are entirely whitespace are holes.
+    fn parse_cells(layout: &str, cell_width: usize, start_key: &str) -> Result<Keypad> {
+        if cell_width == 0 {
+            return Err(eyre!("Invalid cell width: 0"));
+        }
+
+        let rows: Vec<Vec<_>> = layout
+            .lines()
+            .map(|line| {
+                let chars: Vec<_> = line.chars().collect();
+                chars
+                    .chunks(cell_width)
+                    .map(|cell| {
+                        let label: String = cell.iter().collect();
+                        let label = label.trim();
+                        (!label.is_empty()).then(|| label.to_owned())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut keys = Vec::with_capacity(width * rows.len());
+        for mut row in rows {
+            row.resize(width, None);
+            keys.extend(row);
+        }
+
+        let mut keypad = Keypad {
+            width,
+            height: keys.len() / width.max(1),
+            keys,
+            start: (0, 0),
+            edge: Edge::Clamp,
+        };
+        keypad.start = keypad
+            .key_pos(start_key)
+            .ok_or_else(|| eyre!("Start key not in layout: {}", start_key))?;
+
+        Ok(keypad)
+    }
+
+    fn with_edge(mut self, edge: Edge) -> Result<Keypad> {
+        if let Edge::Teleport(target) = &edge {
+            if self.key_pos(target).is_none() {
+                return Err(eyre!("Teleport key not in layout: {}", target));
             }
         }
 
-        let new_digit = y * 3 + x + 1;
-        number = number * 10 + new_digit;
+        self.edge = edge;
+        Ok(self)
     }
 
-    Ok(number)
-}
+    fn key(&self, (x, y): (usize, usize)) -> Option<&str> {
+        if x < self.width && y < self.height {
+            self.keys[y * self.width + x].as_deref()
+        } else {
+            None
+        }
+    }
 
-fn part2(instrs: &[Vec<Instruction>]) -> Result<String> {
-    #[rustfmt::skip]
-    let grid = [
-        ' ', ' ', '1', ' ', ' ',
-        ' ', '2', '3', '4', ' ',
-        '5', '6', '7', '8', '9',
-        ' ', 'A', 'B', 'C', ' ',
-        ' ', ' ', 'D', ' ', ' ',
-    ];
+    fn key_pos(&self, label: &str) -> Option<(usize, usize)> {
+        self.keys
+            .iter()
+            .position(|k| k.as_deref() == Some(label))
+            .map(|idx| (idx % self.width, idx / self.width))
+    }
 
-    let mut number = String::new();
+    // Returns None if the move is blocked.
+    fn step(&self, (x, y): (usize, usize), instr: &Instruction) -> Option<(usize, usize)> {
+        let next = instr.apply((x, y));
+        if self.key(next).is_some() {
+            return Some(next);
+        }
 
-    let mut x: usize = 0;
-    let mut y: usize = 2;
+        match &self.edge {
+            Edge::Clamp => Some((x, y)),
+            Edge::Block => None,
+            Edge::Teleport(target) => self.key_pos(target),
+            Edge::Wrap => {
+                let wrap = |(x, y): (usize, usize)| {
+                    let x = match x {
+                        usize::MAX => self.width - 1,
+                        x if x >= self.width => 0,
+                        x => x,
+                    };
+                    let y = match y {
+                        usize::MAX => self.height - 1,
+                        y if y >= self.height => 0,
+                        y => y,
+                    };
+                    (x, y)
+                };
 
-    for digit_instrs in instrs {
-        for instr in digit_instrs {
-            match instr {
-                Instruction::Up => {
-                    let maybe_new = y.saturating_sub(1);
-                    if grid[maybe_new * 5 + x] != ' ' {
-                        y = maybe_new;
-                    }
+                // Keep going around the row or column until we find a key, which at worst is
+                // the one we started on.
+                let mut next = wrap(next);
+                while self.key(next).is_none() {
+                    next = wrap(instr.apply(next));
                 }
-                Instruction::Down => {
-                    let maybe_new = (y + 1).min(4);
-                    if grid[maybe_new * 5 + x] != ' ' {
-                        y = maybe_new;
-                    }
+
+                Some(next)
+            }
+        }
+    }
+
+    fn solve_labels(&self, instrs: &[Vec<Instruction>]) -> Result<Vec<&str>> {
+        let mut pos = self.start;
+        let mut code = Vec::with_capacity(instrs.len());
+
+        for (line, digit_instrs) in instrs.iter().enumerate() {
+            for (i, instr) in digit_instrs.iter().enumerate() {
+                pos = self
+                    .step(pos, instr)
+                    .ok_or_else(|| eyre!("Line {} blocked at instruction {}", line + 1, i + 1))?;
+            }
+            code.extend(self.key(pos));
+        }
+
+        Ok(code)
+    }
+
+    fn solve(&self, instrs: &[Vec<Instruction>]) -> Result<String> {
+        Ok(self.solve_labels(instrs)?.concat())
+    }
+
+    fn shortest_path(&self, from: (usize, usize), to: (usize, usize)) -> Vec<Instruction> {
+        let mut prev = vec![None; self.keys.len()];
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(pos) = queue.pop_front() {
+            if pos == to {
+                break;
+            }
+
+            for dir in Instruction::ALL {
+                let next = match self.step(pos, &dir) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                let idx = next.1 * self.width + next.0;
+                if next != from && prev[idx].is_none() {
+                    prev[idx] = Some((pos, dir));
+                    queue.push_back(next);
                 }
-                Instruction::Left => {
-                    let maybe_new = x.saturating_sub(1);
-                    if grid[y * 5 + maybe_new] != ' ' {
-                        x = maybe_new;
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut pos = to;
+        while let Some((prev_pos, dir)) = prev[pos.1 * self.width + pos.0] {
+            path.push(dir);
+            pos = prev_pos;
+        }
+        path.reverse();
+
+        path
+    }
+
+    fn synthesise(&self, code: &[&str]) -> Result<Vec<Vec<Instruction>>> {
+        let mut pos = self.start;
+        let mut lines = Vec::new();
+
+        for &label in code {
+            let target = self
+                .key_pos(label)
+                .ok_or_else(|| eyre!("Key not in layout: {}", label))?;
+
+            let path = self.shortest_path(pos, target);
+            if path.is_empty() && pos != target {
+                return Err(eyre!("Key {} can't be reached", label));
+            }
+
+            lines.push(path);
+            pos = target;
+        }
+
+        let actual = self.solve_labels(&lines)?;
+        if actual != code {
+            return Err(eyre!(
+                "Synthesised lines give {}, not {}",
+                actual.concat(),
+                code.concat()
+            ));
+        }
+
+        Ok(lines)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TraceStep {
+    instr: Instruction,
+    pos: (usize, usize),
+    // The adjacent cell wasn't a key, so the edge behaviour decided where we ended up.
+    blocked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineTrace {
+    start: (usize, usize),
+    steps: Vec<TraceStep>,
+}
+
+impl Keypad {
+    // Unlike solve, a blocking edge doesn't end the trace; we just stay where we are.
+    fn trace(&self, instrs: &[Vec<Instruction>]) -> Vec<LineTrace> {
+        let mut pos = self.start;
+        let mut lines = Vec::with_capacity(instrs.len());
+
+        for digit_instrs in instrs {
+            let start = pos;
+            let steps = digit_instrs
+                .iter()
+                .map(|&instr| {
+                    let blocked = self.key(instr.apply(pos)).is_none();
+                    pos = self.step(pos, &instr).unwrap_or(pos);
+                    TraceStep {
+                        instr,
+                        pos,
+                        blocked,
                     }
-                }
-                Instruction::Right => {
-                    let maybe_new = (x + 1).min(4);
-                    if grid[y * 5 + maybe_new] != ' ' {
-                        x = maybe_new;
+                })
+                .collect();
+
+            lines.push(LineTrace { start, steps });
+        }
+
+        lines
+    }
+
+    fn render_line_trace(&self, buf: &mut String, line: &LineTrace) {
+        buf.push_str(self.key(line.start).unwrap_or("?"));
+        for step in &line.steps {
+            let arrow = if step.blocked { "-|" } else { "->" };
+            write!(
+                buf,
+                " -{}{} {}",
+                step.instr.as_char(),
+                arrow,
+                self.key(step.pos).unwrap_or("?")
+            )
+            .unwrap();
+        }
+    }
+
+    fn render_frame(&self, buf: &mut String, current: (usize, usize)) {
+        let cell_width = self
+            .keys
+            .iter()
+            .flatten()
+            .map(|k| k.chars().count())
+            .max()
+            .unwrap_or(1);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                match self.key((x, y)) {
+                    Some(key) if (x, y) == current => {
+                        write!(buf, "[{:^w$}]", key, w = cell_width).unwrap()
                     }
+                    Some(key) => write!(buf, " {:^w$} ", key, w = cell_width).unwrap(),
+                    None => write!(buf, " {:^w$} ", "", w = cell_width).unwrap(),
                 }
             }
+            buf.push('\n');
+        }
+    }
+
+    fn trace_frames(&self, trace: &[LineTrace]) -> Vec<String> {
+        let mut frames = Vec::new();
+        let mut code = String::new();
+
+        for (line_idx, line) in trace.iter().enumerate() {
+            let mut frame = String::new();
+            self.render_frame(&mut frame, line.start);
+            writeln!(&mut frame, "Line {}, start. Code: {}", line_idx + 1, code).unwrap();
+            frames.push(frame);
+
+            for (step_idx, step) in line.steps.iter().enumerate() {
+                let mut frame = String::new();
+                self.render_frame(&mut frame, step.pos);
+                writeln!(
+                    &mut frame,
+                    "Line {}, step {}/{}: {}{}. Code: {}",
+                    line_idx + 1,
+                    step_idx + 1,
+                    line.steps.len(),
+                    step.instr.as_char(),
+                    if step.blocked { " (blocked)" } else { "" },
+                    code
+                )
+                .unwrap();
+                frames.push(frame);
+            }
+
+            let end = line.steps.last().map_or(line.start, |s| s.pos);
+            code.push_str(self.key(end).unwrap_or("?"));
+        }
+
+        frames
+    }
+}
+
+// Keys are numbered in reading order, skipping holes.
+struct TransitionTable {
+    keys: Vec<String>,
+    next: Vec<[usize; 4]>,
+    start: usize,
+}
+
+impl TransitionTable {
+    fn new(keypad: &Keypad) -> Result<Self> {
+        if keypad.edge == Edge::Block {
+            return Err(eyre!("Transition tables don't support blocking edges"));
+        }
+
+        let positions: Vec<_> = (0..keypad.height)
+            .flat_map(|y| (0..keypad.width).map(move |x| (x, y)))
+            .filter(|&pos| keypad.key(pos).is_some())
+            .collect();
+        let key_id = |pos| positions.iter().position(|&p| p == pos).unwrap();
+
+        let next = positions
+            .iter()
+            .map(|&pos| Instruction::ALL.map(|dir| key_id(keypad.step(pos, &dir).unwrap())))
+            .collect();
+
+        Ok(TransitionTable {
+            keys: positions
+                .iter()
+                .filter_map(|&pos| keypad.key(pos))
+                .map(str::to_owned)
+                .collect(),
+            next,
+            start: key_id(keypad.start),
+        })
+    }
+
+    fn solve(&self, instrs: &[Vec<Instruction>]) -> String {
+        let mut key = self.start;
+        let mut code = String::with_capacity(instrs.len());
+
+        for digit_instrs in instrs {
+            key = digit_instrs
+                .iter()
+                .fold(key, |key, &instr| self.next[key][instr as usize]);
+            code.push_str(&self.keys[key]);
+        }
+
+        code
+    }
+
+    fn line_transition(&self, line: &[Instruction]) -> LineTransition {
+        let mut ends: Vec<_> = (0..self.keys.len()).collect();
+        for &instr in line {
+            ends.iter_mut()
+                .for_each(|key| *key = self.next[*key][instr as usize]);
         }
 
-        let new_digit = grid[y * 5 + x];
-        number.push(new_digit);
+        LineTransition(ends)
     }
 
-    Ok(number)
+    // Identical lines share a cached transition, and each line is combined with the
+    // transitions before it, so each digit is a single lookup from the start key.
+    fn solve_composed(&self, instrs: &[Vec<Instruction>]) -> String {
+        let mut cache: HashMap<&[Instruction], LineTransition> = HashMap::new();
+        let mut combined = LineTransition((0..self.keys.len()).collect());
+        let mut code = String::with_capacity(instrs.len());
+
+        for digit_instrs in instrs {
+            let transition = cache
+                .entry(digit_instrs)
+                .or_insert_with(|| self.line_transition(digit_instrs));
+            combined = combined.then(transition);
+            code.push_str(&self.keys[combined.apply(self.start)]);
+        }
+
+        code
+    }
+}
+
+// Maps each starting key to the key the line ends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineTransition(Vec<usize>);
+
+impl LineTransition {
+    fn apply(&self, key: usize) -> usize {
+        self.0[key]
+    }
+
+    fn then(&self, next: &LineTransition) -> LineTransition {
+        LineTransition(self.0.iter().map(|&key| next.apply(key)).collect())
+    }
 }
 
 #[cfg(test)]
@@ -136,8 +634,9 @@ mod tests_1602 {
         UUUUD";
 
         let instructions = Instruction::parse(input).unwrap();
-        let expected = 1985;
-        let actual = part1(&instructions).unwrap();
+        let expected = "1985";
+        let keypad = Keypad::parse(PART1_LAYOUT, "5").unwrap();
+        let actual = keypad.solve(&instructions).unwrap();
 
         assert_eq!(actual, expected);
     }
@@ -150,8 +649,143 @@ mod tests_1602 {
 
         let instructions = Instruction::parse(input).unwrap();
         let expected = "5DB3";
-        let actual = part2(&instructions).unwrap();
+        let keypad = Keypad::parse(PART2_LAYOUT, "5").unwrap();
+        let actual = keypad.solve(&instructions).unwrap();
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn keypad_parse() {
+        let keypad = Keypad::parse(PART2_LAYOUT, "5").unwrap();
+        assert_eq!((keypad.width, keypad.height), (5, 5));
+        assert_eq!(keypad.start, (0, 2));
+        assert_eq!(keypad.key((2, 0)), Some("1"));
+        assert_eq!(keypad.key((0, 0)), None);
+        assert_eq!(keypad.key((4, 4)), None);
+
+        assert!(Keypad::parse(PART1_LAYOUT, "A").is_err());
+    }
+
+    #[test]
+    fn transition_table() {
+        let input = "ULL
+        RRDDD
+        LURDL
+        UUUUD";
+        let instructions = Instruction::parse(input).unwrap();
+
+        for (layout, expected) in [(PART1_LAYOUT, "1985"), (PART2_LAYOUT, "5DB3")] {
+            let table = TransitionTable::new(&Keypad::parse(layout, "5").unwrap()).unwrap();
+            assert_eq!(table.solve(&instructions), expected);
+            assert_eq!(table.solve_composed(&instructions), expected);
+        }
+
+        let table = TransitionTable::new(&Keypad::parse(PART2_LAYOUT, "5").unwrap()).unwrap();
+        let first = table.line_transition(&instructions[0]);
+        let second = table.line_transition(&instructions[1]);
+        let both: Vec<_> = instructions[0]
+            .iter()
+            .chain(&instructions[1])
+            .copied()
+            .collect();
+        assert_eq!(first.then(&second), table.line_transition(&both));
+    }
+
+    #[test]
+    fn synthesise() {
+        let keypad = Keypad::parse(PART2_LAYOUT, "5").unwrap();
+        let lines = keypad.synthesise(&["5", "D", "B", "3"]).unwrap();
+        let lines: Vec<String> = lines
+            .into_iter()
+            .map(|l| l.into_iter().map(Instruction::as_char).collect())
+            .collect();
+
+        assert_eq!(lines, ["", "RDRD", "U", "UU"]);
+
+        let keypad = Keypad::parse(PART1_LAYOUT, "5").unwrap();
+        let lines = keypad.synthesise(&["1", "9", "8", "5"]).unwrap();
+        assert_eq!(lines.iter().map(Vec::len).collect::<Vec<_>>(), [2, 4, 1, 1]);
+
+        assert!(keypad.synthesise(&["1", "A"]).is_err());
+    }
+
+    #[test]
+    fn edges() {
+        let layout = "10 11 12\n13    15";
+        let instructions = Instruction::parse("DR\nRR\nL").unwrap();
+
+        let keypad = Keypad::parse_cells(layout, 3, "10").unwrap();
+        assert_eq!(
+            keypad.solve_labels(&instructions).unwrap(),
+            ["13", "13", "13"]
+        );
+
+        let keypad = keypad.with_edge(Edge::Wrap).unwrap();
+        assert_eq!(
+            keypad.solve_labels(&instructions).unwrap(),
+            ["15", "15", "13"]
+        );
+
+        let keypad = keypad.with_edge(Edge::Teleport("12".to_owned())).unwrap();
+        assert_eq!(
+            keypad.solve_labels(&instructions).unwrap(),
+            ["12", "12", "11"]
+        );
+        let table = TransitionTable::new(&keypad).unwrap();
+        assert_eq!(table.solve(&instructions), "121211");
+
+        let keypad = keypad.with_edge(Edge::Block).unwrap();
+        assert!(keypad.solve(&instructions).is_err());
+        assert!(TransitionTable::new(&keypad).is_err());
+
+        let keypad = keypad.with_edge(Edge::Wrap).unwrap();
+        let lines = keypad.synthesise(&["15", "10"]).unwrap();
+        assert_eq!(
+            lines,
+            [
+                vec![Instruction::Up, Instruction::Left],
+                vec![Instruction::Up, Instruction::Right]
+            ]
+        );
+
+        assert!(keypad.with_edge(Edge::Teleport("14".to_owned())).is_err());
+    }
+
+    #[test]
+    fn trace() {
+        let keypad = Keypad::parse(PART2_LAYOUT, "5").unwrap();
+        let instructions = Instruction::parse("ULL\nRRDDD").unwrap();
+        let trace = keypad.trace(&instructions);
+
+        let mut actual = String::new();
+        for line in &trace {
+            keypad.render_line_trace(&mut actual, line);
+            actual.push('\n');
+        }
+        let expected = "5 -U-| 5 -L-| 5 -L-| 5\n5 -R-> 6 -R-> 7 -D-> B -D-> D -D-| D\n";
+        assert_eq!(actual, expected);
+
+        let frames = keypad.trace_frames(&trace);
+        assert_eq!(frames.len(), 10);
+        let expected = concat!(
+            "       1       \n",
+            "    2  3  4    \n",
+            " 5  6  7  8  9 \n",
+            "    A  B  C    \n",
+            "      [D]      \n",
+            "Line 2, step 5/5: D (blocked). Code: 5\n",
+        );
+        assert_eq!(frames[9], expected);
+
+        assert_eq!(frame_delay(frames.len()), Duration::from_millis(100));
+        assert_eq!(frame_delay(5_000), Duration::from_millis(2));
+        assert_eq!(frame_delay(0), Duration::from_millis(100));
+
+        // Cells are padded by characters, not bytes.
+        let keypad = Keypad::parse_cells("αβ γ", 3, "αβ").unwrap();
+        let mut frame = String::new();
+        keypad.render_frame(&mut frame, (1, 0));
+        assert_eq!(frame, " αβ [γ ]\n");
+    }
 }
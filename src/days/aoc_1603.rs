@@ -1,13 +1,22 @@
-use aoc_lib::{Bench, BenchError, BenchResult, Day};
+use std::{
+    fmt::Display,
+    iter::Enumerate,
+    str::{FromStr, Lines},
+};
+
+use aoc_lib::{Bench, BenchError, BenchResult, Day, NoError};
 use color_eyre::eyre::{eyre, Result};
-use itertools::Itertools;
 
 pub const DAY: Day = Day {
     day: 3,
     name: "Squares With Three Sides",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[
+        ("Report", run_report),
+        ("Part 1 Batched", run_part1_batched),
+        ("Part 2 Batched", run_part2_batched),
+    ],
 };
 
 fn run_part1(input: &str, b: Bench) -> BenchResult {
@@ -20,60 +29,336 @@ fn run_part2(input: &str, b: Bench) -> BenchResult {
     b.bench(|| valid_tris(&tris))
 }
 
-fn parse_p1(input: &str) -> Result<Vec<[u16; 3]>> {
-    input
-        .lines()
-        .map(|line| {
-            let mut parts = line.split_whitespace().map(str::parse);
-            let ((a, b), c) = parts
+fn run_part1_batched(input: &str, b: Bench) -> BenchResult {
+    let tris = parse_p1(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let tris = TriangleBatch::new(&tris);
+    b.bench(|| Ok::<_, NoError>(tris.count_valid()))
+}
+
+fn run_part2_batched(input: &str, b: Bench) -> BenchResult {
+    let tris = parse_p2(input).map_err(|e| BenchError::UserError(e.into()))?;
+    let tris = TriangleBatch::new(&tris);
+    b.bench(|| Ok::<_, NoError>(tris.count_valid()))
+}
+
+fn run_report(input: &str, b: Bench) -> BenchResult {
+    let tris = parse_p1(input).map_err(|e| BenchError::UserError(e.into()))?;
+    b.bench_alt(|| Ok::<_, NoError>(TriangleReport::new(&tris).to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    RowMajor,
+    ColumnMajor,
+}
+
+// Reads a whitespace-separated table of numbers in groups of N. For column-major order, each
+// block of N lines is transposed, so each group is N lines from the same column.
+struct TableReader<'a, T, const N: usize> {
+    lines: Enumerate<Lines<'a>>,
+    order: Order,
+    block: [[T; N]; N],
+    next_col: usize,
+}
+
+impl<'a, T: Copy + Default, const N: usize> TableReader<'a, T, N> {
+    fn new(input: &'a str, order: Order) -> Self {
+        Self {
+            lines: input.lines().enumerate(),
+            order,
+            block: [[T::default(); N]; N],
+            next_col: N,
+        }
+    }
+}
+
+impl<T, const N: usize> TableReader<'_, T, N>
+where
+    T: FromStr + Copy + Default,
+    T::Err: Display,
+{
+    fn parse_row((idx, line): (usize, &str)) -> Result<[T; N]> {
+        let line_num = idx + 1;
+        let mut parts = line.split_whitespace();
+        let mut row = [T::default(); N];
+
+        for cell in &mut row {
+            let part = parts
                 .next()
-                .zip(parts.next())
-                .zip(parts.next())
-                .ok_or_else(|| eyre!("Expected 3 numbers"))?;
+                .ok_or_else(|| eyre!("Line {}: expected {} numbers", line_num, N))?;
+            *cell = part
+                .parse()
+                .map_err(|e| eyre!("Line {}: invalid number {:?}: {}", line_num, part, e))?;
+        }
 
-            Ok([a?, b?, c?])
-        })
-        .collect()
+        if parts.next().is_some() {
+            return Err(eyre!("Line {}: expected only {} numbers", line_num, N));
+        }
+
+        Ok(row)
+    }
+}
+
+impl<T, const N: usize> Iterator for TableReader<'_, T, N>
+where
+    T: FromStr + Copy + Default,
+    T::Err: Display,
+{
+    type Item = Result<[T; N]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.order {
+            Order::RowMajor => self.lines.next().map(Self::parse_row),
+            Order::ColumnMajor => {
+                if self.next_col == N {
+                    let mut first_line = None;
+                    for (i, row) in self.block.iter_mut().enumerate() {
+                        let line = match self.lines.next() {
+                            Some(line) => line,
+                            None => {
+                                // Running out at the start of a block is just the end of the input.
+                                let first = first_line? + 1;
+                                let last = first + i - 1;
+                                let err = if first == last {
+                                    eyre!("Incomplete group of {}: line {} is left over", N, first)
+                                } else {
+                                    eyre!(
+                                        "Incomplete group of {}: lines {}-{} are left over",
+                                        N,
+                                        first,
+                                        last
+                                    )
+                                };
+                                return Some(Err(err));
+                            }
+                        };
+                        first_line.get_or_insert(line.0);
+
+                        *row = match Self::parse_row(line) {
+                            Ok(r) => r,
+                            Err(e) => return Some(Err(e)),
+                        };
+                    }
+                    self.next_col = 0;
+                }
+
+                let col = self.next_col;
+                self.next_col += 1;
+                Some(Ok(std::array::from_fn(|row| self.block[row][col])))
+            }
+        }
+    }
+}
+
+fn parse_p1(input: &str) -> Result<Vec<[u16; 3]>> {
+    TableReader::new(input, Order::RowMajor).collect()
 }
 
 fn parse_p2(input: &str) -> Result<Vec<[u16; 3]>> {
-    input
-        .lines()
-        .map(|line| -> Result<_> {
-            let mut parts = line.split_whitespace().map(str::parse);
-            let ((a, b), c) = parts
-                .next()
-                .zip(parts.next())
-                .zip(parts.next())
-                .ok_or_else(|| eyre!("Expected 3 numbers"))?;
+    TableReader::new(input, Order::ColumnMajor).collect()
+}
 
-            Ok([a?, b?, c?])
-        })
-        .tuples()
-        .map(|(a, b, c)| -> Result<_> {
-            let [a1, b1, c1] = a?;
-            let [a2, b2, c2] = b?;
-            let [a3, b3, c3] = c?;
-            Ok([[a1, a2, a3], [b1, b2, b3], [c1, c2, c3]])
-        })
-        .try_fold(Vec::new(), |mut acc, trio| {
-            acc.extend(trio?.as_ref());
-            Ok(acc)
-        })
+// Widened so the side sums can't overflow.
+fn sorted_sides(tri: [u16; 3]) -> [u32; 3] {
+    let mut sides = tri.map(u32::from);
+    sides.sort_unstable();
+    sides
 }
 
 fn valid_tris(tris: &[[u16; 3]]) -> Result<usize> {
     Ok(tris
         .iter()
-        .filter(|[a, b, c]| a + b > *c && a + c > *b && b + c > *a)
+        .filter(|tri| {
+            let [a, b, c] = tri.map(u32::from);
+            a + b > c && a + c > b && b + c > a
+        })
         .count())
 }
 
+// Structure-of-arrays layout, so the validity check can be vectorised.
+#[derive(Debug, Default)]
+struct TriangleBatch {
+    a: Vec<u16>,
+    b: Vec<u16>,
+    c: Vec<u16>,
+}
+
+impl TriangleBatch {
+    const LANES: usize = 16;
+
+    fn new(tris: &[[u16; 3]]) -> Self {
+        let mut batch = TriangleBatch {
+            a: Vec::with_capacity(tris.len()),
+            b: Vec::with_capacity(tris.len()),
+            c: Vec::with_capacity(tris.len()),
+        };
+
+        for &[a, b, c] in tris {
+            batch.a.push(a);
+            batch.b.push(b);
+            batch.c.push(c);
+        }
+
+        batch
+    }
+
+    fn is_valid(a: u16, b: u16, c: u16) -> bool {
+        let (a, b, c) = (u32::from(a), u32::from(b), u32::from(c));
+        // Non-short-circuiting so there's no branches to get in the way of vectorising.
+        (a + b > c) & (a + c > b) & (b + c > a)
+    }
+
+    fn count_valid(&self) -> usize {
+        const LANES: usize = TriangleBatch::LANES;
+
+        let a_chunks = self.a.chunks_exact(LANES);
+        let b_chunks = self.b.chunks_exact(LANES);
+        let c_chunks = self.c.chunks_exact(LANES);
+
+        let remainder = a_chunks
+            .remainder()
+            .iter()
+            .zip(b_chunks.remainder())
+            .zip(c_chunks.remainder())
+            .filter(|((&a, &b), &c)| Self::is_valid(a, b, c))
+            .count();
+
+        // Each lane counts separately, and they're only summed at the end.
+        let mut counts = [0_usize; LANES];
+        for ((a, b), c) in a_chunks.zip(b_chunks).zip(c_chunks) {
+            let a: &[u16; LANES] = a.try_into().unwrap();
+            let b: &[u16; LANES] = b.try_into().unwrap();
+            let c: &[u16; LANES] = c.try_into().unwrap();
+
+            for i in 0..LANES {
+                counts[i] += Self::is_valid(a[i], b[i], c[i]) as usize;
+            }
+        }
+
+        counts.iter().sum::<usize>() + remainder
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TriangleKind {
+    Equilateral,
+    Isosceles,
+    Scalene,
+    Degenerate,
+    Impossible,
+}
+
+impl TriangleKind {
+    fn classify(tri: [u16; 3]) -> Self {
+        let [a, b, c] = sorted_sides(tri);
+        match (a + b).cmp(&c) {
+            std::cmp::Ordering::Less => TriangleKind::Impossible,
+            std::cmp::Ordering::Equal => TriangleKind::Degenerate,
+            std::cmp::Ordering::Greater if a == c => TriangleKind::Equilateral,
+            std::cmp::Ordering::Greater if a == b || b == c => TriangleKind::Isosceles,
+            std::cmp::Ordering::Greater => TriangleKind::Scalene,
+        }
+    }
+}
+
+// Uses the rearrangement of Heron's formula that's stable for needle-like triangles. Anything
+// that isn't a real triangle has no area.
+fn heron_area(tri: [u16; 3]) -> f64 {
+    let [c, b, a] = sorted_sides(tri).map(f64::from);
+    if a >= b + c {
+        return 0.0;
+    }
+
+    let product = (a + (b + c)) * (c - (a - b)) * (c + (a - b)) * (a + (b - c));
+    product.sqrt() / 4.0
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct TriangleReport {
+    equilateral: usize,
+    isosceles: usize,
+    scalene: usize,
+    degenerate: usize,
+    impossible: usize,
+    total_area: f64,
+    max_area: f64,
+    // Perimeters only cover real triangles.
+    min_perimeter: Option<u32>,
+    max_perimeter: Option<u32>,
+    total_perimeter: u64,
+}
+
+impl TriangleReport {
+    fn new(tris: &[[u16; 3]]) -> Self {
+        let mut report = TriangleReport::default();
+
+        for &tri in tris {
+            let kind = TriangleKind::classify(tri);
+            match kind {
+                TriangleKind::Equilateral => report.equilateral += 1,
+                TriangleKind::Isosceles => report.isosceles += 1,
+                TriangleKind::Scalene => report.scalene += 1,
+                TriangleKind::Degenerate => report.degenerate += 1,
+                TriangleKind::Impossible => report.impossible += 1,
+            }
+
+            if matches!(kind, TriangleKind::Degenerate | TriangleKind::Impossible) {
+                continue;
+            }
+
+            let area = heron_area(tri);
+            report.total_area += area;
+            report.max_area = report.max_area.max(area);
+
+            let perimeter: u32 = sorted_sides(tri).iter().sum();
+            report.min_perimeter =
+                Some(report.min_perimeter.map_or(perimeter, |p| p.min(perimeter)));
+            report.max_perimeter =
+                Some(report.max_perimeter.map_or(perimeter, |p| p.max(perimeter)));
+            report.total_perimeter += u64::from(perimeter);
+        }
+
+        report
+    }
+
+    fn num_real(&self) -> usize {
+        self.equilateral + self.isosceles + self.scalene
+    }
+}
+
+impl Display for TriangleReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Equilateral: {}", self.equilateral)?;
+        writeln!(f, "Isosceles:   {}", self.isosceles)?;
+        writeln!(f, "Scalene:     {}", self.scalene)?;
+        writeln!(f, "Degenerate:  {}", self.degenerate)?;
+        writeln!(f, "Impossible:  {}", self.impossible)?;
+
+        if let (Some(min), Some(max)) = (self.min_perimeter, self.max_perimeter) {
+            let mean_perimeter = self.total_perimeter as f64 / self.num_real() as f64;
+            let mean_area = self.total_area / self.num_real() as f64;
+            writeln!(
+                f,
+                "Perimeter:   min {}, max {}, mean {:.2}",
+                min, max, mean_perimeter
+            )?;
+            writeln!(
+                f,
+                "Area:        max {:.2}, mean {:.2}",
+                self.max_area, mean_area
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests_1603 {
     use aoc_lib::Example;
 
     use super::*;
+    use crate::rng::Rng;
 
     #[test]
     fn part1_test() {
@@ -99,4 +384,140 @@ mod tests_1603 {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn table_reader_test() {
+        let input = "1 2\n3 4\n5 6\n7 8";
+
+        let actual: Vec<[u8; 2]> = TableReader::new(input, Order::RowMajor)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(actual, [[1, 2], [3, 4], [5, 6], [7, 8]]);
+
+        let actual: Vec<[u8; 2]> = TableReader::new(input, Order::ColumnMajor)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(actual, [[1, 3], [2, 4], [5, 7], [6, 8]]);
+
+        let input = "1 2 3 4\n5 6 7 8\n9 10 11 12\n13 14 15 16";
+        let actual: Vec<[u8; 4]> = TableReader::new(input, Order::ColumnMajor)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            actual,
+            [
+                [1, 5, 9, 13],
+                [2, 6, 10, 14],
+                [3, 7, 11, 15],
+                [4, 8, 12, 16]
+            ]
+        );
+
+        assert!(TableReader::<u8, 3>::new("1 2", Order::RowMajor)
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_errors_test() {
+        let err = |input: &str, order| {
+            TableReader::<u16, 3>::new(input, order)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err()
+                .to_string()
+        };
+
+        let input = "1 2 3\n4 5 6\n7 8 9\n10 11 12\n13 14 15";
+        assert_eq!(
+            err(input, Order::ColumnMajor),
+            "Incomplete group of 3: lines 4-5 are left over"
+        );
+        assert_eq!(
+            err("1 2 3\n4 5 6\n7 8 9\n10 11 12", Order::ColumnMajor),
+            "Incomplete group of 3: line 4 is left over"
+        );
+        assert_eq!(
+            err("1 2 3\n4 x 6", Order::RowMajor),
+            "Line 2: invalid number \"x\": invalid digit found in string"
+        );
+        assert_eq!(
+            err("1 2 3\n4 5 6 7", Order::ColumnMajor),
+            "Line 2: expected only 3 numbers"
+        );
+        assert_eq!(err("1 2", Order::RowMajor), "Line 1: expected 3 numbers");
+    }
+
+    #[test]
+    fn large_sides_test() {
+        let tris = [
+            [40_000, 40_000, 50_000],
+            [30_000, 35_000, 65_000],
+            [65_535, 1, 1],
+        ];
+        assert_eq!(valid_tris(&tris).unwrap(), 1);
+    }
+
+    #[test]
+    fn classify_test() {
+        let tests = [
+            ([5, 5, 5], TriangleKind::Equilateral),
+            ([5, 8, 5], TriangleKind::Isosceles),
+            ([3, 4, 5], TriangleKind::Scalene),
+            ([2, 3, 5], TriangleKind::Degenerate),
+            ([0, 5, 5], TriangleKind::Degenerate),
+            ([5, 10, 25], TriangleKind::Impossible),
+            ([65_535, 65_535, 65_535], TriangleKind::Equilateral),
+        ];
+
+        for (tri, expected) in tests {
+            assert_eq!(TriangleKind::classify(tri), expected, "{:?}", tri);
+        }
+
+        assert_eq!(heron_area([3, 4, 5]), 6.0);
+        assert_eq!(heron_area([2, 3, 5]), 0.0);
+        assert_eq!(heron_area([5, 10, 25]), 0.0);
+    }
+
+    #[test]
+    fn report_test() {
+        let report = TriangleReport::new(&[[3, 4, 5], [6, 8, 10], [2, 3, 5], [5, 10, 25]]);
+        let expected = TriangleReport {
+            scalene: 2,
+            degenerate: 1,
+            impossible: 1,
+            total_area: 30.0,
+            max_area: 24.0,
+            min_perimeter: Some(12),
+            max_perimeter: Some(24),
+            total_perimeter: 36,
+            ..Default::default()
+        };
+
+        assert_eq!(report, expected);
+        assert!(report.to_string().ends_with(
+            "Perimeter:   min 12, max 24, mean 18.00\nArea:        max 24.00, mean 15.00\n"
+        ));
+    }
+
+    #[test]
+    fn batched_test() {
+        let tris = [[5, 10, 25], [3, 4, 5], [65_535, 65_535, 1], [2, 3, 5]];
+        assert_eq!(TriangleBatch::new(&tris).count_valid(), 2);
+
+        // Sides are generated around 60000 so plenty of the sums would overflow a u16.
+        let mut rng = Rng::new(0x1234_5678);
+        let mut next_side = || (60_000 + rng.below(5_536)) as u16 / (rng.below(16) as u16 + 1);
+
+        let num_tris = 20_000_003;
+        let tris: Vec<_> = (0..num_tris)
+            .map(|_| [next_side(), next_side(), next_side()])
+            .collect();
+
+        let expected = valid_tris(&tris).unwrap();
+        let actual = TriangleBatch::new(&tris).count_valid();
+
+        assert!(expected > 0 && expected < num_tris);
+        assert_eq!(actual, expected);
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 use aoc_lib::{Bench, BenchError, BenchResult, Day, NoError};
 use color_eyre::eyre::{eyre, Result};
 
@@ -6,7 +8,14 @@ pub const DAY: Day = Day {
     name: "Security Through Obscurity",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[
+        ("Round Trip", run_round_trip),
+        ("Directory", run_directory),
+        ("Search", run_search),
+        ("Decoys", run_decoys),
+        ("Crack", run_crack),
+        ("Part 1 Strict", run_part1_strict),
+    ],
 };
 
 fn run_part1(input: &str, b: Bench) -> BenchResult {
@@ -42,6 +51,553 @@ fn run_part2(input: &str, b: Bench) -> BenchResult {
     })
 }
 
+fn run_part1_strict(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .map(|(i, line)| Room::parse_strict(line).map_err(|e| eyre!("Line {}: {}", i + 1, e)))
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| Ok::<u32, NoError>(rooms.iter().filter(|r| r.is_real()).map(|r| r.id).sum()))
+}
+
+fn run_round_trip(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Room::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench(|| {
+        let mut plaintext = String::new();
+        let mut count = 0;
+        for room in rooms.iter().filter(|r| r.is_real()) {
+            plaintext.clear();
+            room.decrypt_name(&mut plaintext);
+
+            let line = generate_room(&plaintext, room.id);
+            let generated = Room::parse(&line)?;
+            if generated != *room {
+                return Err(eyre!("Room {} regenerated as {}", room.id, line));
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    })
+}
+
+fn run_directory(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Room::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench_alt(|| {
+        let directory = RoomDirectory::new(&rooms);
+        let mut output = String::new();
+        for (name, id) in directory.list() {
+            writeln!(output, "{:>4} {}", id, name)?;
+        }
+
+        Ok::<_, std::fmt::Error>(output)
+    })
+}
+
+// The query can be set with AOC_1604_SEARCH, so new keywords don't need a code change.
+fn run_search(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Room::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+    let query = std::env::var("AOC_1604_SEARCH").unwrap_or_else(|_| "northpole object".to_owned());
+    let directory = RoomDirectory::new(&rooms);
+
+    b.bench_alt(|| {
+        let mut output = String::new();
+        writeln!(output, "Query: {:?}", query)?;
+        writeln!(output, "Substring: {:?}", directory.find_substring(&query))?;
+        writeln!(output, "Prefix: {:?}", directory.find_word_prefix(&query))?;
+        write!(output, "Fuzzy: {:?}", directory.find_fuzzy(&query))?;
+
+        Ok::<_, std::fmt::Error>(output)
+    })
+}
+
+fn run_decoys(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Room::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+    b.bench_alt(|| {
+        let mut output = String::new();
+        for report in rooms.iter().map(|&r| ChecksumReport::new(r)) {
+            if !report.is_real() {
+                write!(output, "{}", report)?;
+            }
+        }
+
+        Ok::<_, std::fmt::Error>(output)
+    })
+}
+
+fn run_crack(input: &str, b: Bench) -> BenchResult {
+    let rooms: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Room::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|e| BenchError::UserError(e.into()))?;
+    let real: Vec<_> = rooms.iter().filter(|r| r.is_real()).collect();
+
+    b.bench(|| {
+        let recovered = real
+            .iter()
+            .filter(|r| {
+                let candidates = crack_name(r.name);
+                candidates[0].shift == r.id % 26
+            })
+            .count();
+
+        Ok::<_, NoError>(format!("{}/{} shifts recovered", recovered, real.len()))
+    })
+}
+
+#[derive(Debug, Default)]
+struct RoomDirectory {
+    // Decrypted name and sector id of each real room, sorted by name.
+    entries: Vec<(String, u32)>,
+}
+
+impl RoomDirectory {
+    fn new(rooms: &[Room]) -> Self {
+        let mut entries: Vec<_> = rooms
+            .iter()
+            .filter(|r| r.is_real())
+            .map(|r| {
+                let mut name = String::new();
+                r.decrypt_name(&mut name);
+                (name, r.id)
+            })
+            .collect();
+        entries.sort();
+
+        RoomDirectory { entries }
+    }
+
+    fn list(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.entries.iter().map(|(name, id)| (name.as_str(), *id))
+    }
+
+    fn find_substring(&self, query: &str) -> Vec<u32> {
+        self.entries
+            .iter()
+            .filter(|(name, _)| name.contains(query))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+
+    // Every word in the query must be the start of some word in the name.
+    fn find_word_prefix(&self, query: &str) -> Vec<u32> {
+        self.entries
+            .iter()
+            .filter(|(name, _)| {
+                query
+                    .split_whitespace()
+                    .all(|q| name.split_whitespace().any(|w| w.starts_with(q)))
+            })
+            .map(|(_, id)| *id)
+            .collect()
+    }
+
+    // Matches each query word to its closest word in the name, allowing roughly one typo
+    // per three letters. Results are ordered by total edit distance.
+    fn find_fuzzy(&self, query: &str) -> Vec<u32> {
+        let mut matches: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|(name, id)| {
+                let mut total = 0;
+                for q in query.split_whitespace() {
+                    let best = name.split_whitespace().map(|w| edit_distance(q, w)).min()?;
+                    if best > (q.len() / 3).max(1) {
+                        return None;
+                    }
+                    total += best;
+                }
+
+                Some((total, *id))
+            })
+            .collect();
+        matches.sort();
+
+        matches.into_iter().map(|(_, id)| id).collect()
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut prev: Vec<_> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ac) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, &bc) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ac != bc);
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ShiftCipher<'a> {
+    alphabet: &'a [char],
+    // Set when the alphabet is a run of consecutive code points, like `a..=z`, so a
+    // character's index can be found by subtraction instead of a search.
+    contiguous: bool,
+}
+
+impl<'a> ShiftCipher<'a> {
+    const LOWERCASE: ShiftCipher<'static> = match ShiftCipher::new(&[
+        'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
+        's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+    ]) {
+        Ok(cipher) => cipher,
+        Err(_) => panic!("invalid lowercase alphabet"),
+    };
+
+    // Shifts are taken modulo the alphabet length, and decrypting needs each character to map
+    // back to one index, so empty alphabets and repeated characters are rejected.
+    const fn new(alphabet: &'a [char]) -> Result<Self, &'static str> {
+        if alphabet.is_empty() {
+            return Err("alphabet is empty");
+        }
+
+        let mut contiguous = true;
+        let mut i = 1;
+        while i < alphabet.len() {
+            contiguous &= alphabet[i] as u32 == alphabet[0] as u32 + i as u32;
+
+            let mut j = 0;
+            while j < i {
+                if alphabet[j] as u32 == alphabet[i] as u32 {
+                    return Err("alphabet has a repeated character");
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+
+        Ok(ShiftCipher {
+            alphabet,
+            contiguous,
+        })
+    }
+
+    fn index_of(&self, c: char) -> Option<usize> {
+        if self.contiguous {
+            let idx = (c as u32).checked_sub(*self.alphabet.first()? as u32)? as usize;
+            (idx < self.alphabet.len()).then_some(idx)
+        } else {
+            self.alphabet.iter().position(|&a| a == c)
+        }
+    }
+
+    // Characters outside the alphabet are left as they are.
+    fn shift_char(&self, c: char, shift: usize) -> char {
+        match self.index_of(c) {
+            Some(idx) => self.alphabet[(idx + shift) % self.alphabet.len()],
+            None => c,
+        }
+    }
+
+    // Decrypting rotates forwards through the alphabet, as in the puzzle.
+    fn decrypt_shift(&self, key: u32) -> usize {
+        key as usize % self.alphabet.len()
+    }
+
+    fn encrypt_shift(&self, key: u32) -> usize {
+        (self.alphabet.len() - self.decrypt_shift(key)) % self.alphabet.len()
+    }
+
+    fn encrypt<I>(self, text: I, key: u32) -> impl Iterator<Item = char> + 'a
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
+    {
+        let shift = self.encrypt_shift(key);
+        text.into_iter().map(move |c| self.shift_char(c, shift))
+    }
+
+    fn decrypt<I>(self, text: I, key: u32) -> impl Iterator<Item = char> + 'a
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
+    {
+        let shift = self.decrypt_shift(key);
+        text.into_iter().map(move |c| self.shift_char(c, shift))
+    }
+}
+
+// Relative letter frequencies of English text, in percent.
+const ENGLISH_FREQ: [f64; 26] = [
+    8.2, 1.5, 2.8, 4.3, 12.7, 2.2, 2.0, 6.1, 7.0, 0.15, 0.77, 4.0, 2.4, 6.7, 7.5, 1.9, 0.095, 6.0,
+    6.3, 9.1, 2.8, 0.98, 2.4, 0.15, 2.0, 0.074,
+];
+
+const WORDS: &[&str] = &[
+    "a",
+    "and",
+    "basket",
+    "bean",
+    "biohazard",
+    "bunny",
+    "candy",
+    "cane",
+    "center",
+    "chocolate",
+    "classified",
+    "colorful",
+    "containment",
+    "cryogenic",
+    "department",
+    "deployment",
+    "design",
+    "dye",
+    "egg",
+    "engineering",
+    "experimental",
+    "flower",
+    "fuzzy",
+    "grade",
+    "grass",
+    "hunt",
+    "jellybean",
+    "laboratory",
+    "magnetic",
+    "military",
+    "northpole",
+    "object",
+    "of",
+    "operations",
+    "plastic",
+    "projects",
+    "purchasing",
+    "rabbit",
+    "radioactive",
+    "reacting",
+    "receiving",
+    "research",
+    "scavenger",
+    "secret",
+    "shipping",
+    "storage",
+    "technology",
+    "the",
+    "top",
+    "weaponry",
+    "workshop",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Candidate {
+    shift: u32,
+    plaintext: String,
+    confidence: f64,
+}
+
+// Tries every shift, scoring each by how English the letters look plus a bonus for letters
+// covered by known words. The confidences are a softmax over the scores, so they sum to 1.
+fn crack_name(name: &str) -> Vec<Candidate> {
+    const WORD_BONUS: f64 = 2.0;
+
+    let cipher = ShiftCipher::LOWERCASE;
+    let spaced: String = name
+        .chars()
+        .map(|c| if c == '-' { ' ' } else { c })
+        .collect();
+
+    let mut scored: Vec<_> = (0..26)
+        .map(|shift| {
+            let plaintext: String = cipher.decrypt(spaced.chars(), shift).collect();
+            let letter_score: f64 = plaintext
+                .chars()
+                .filter(char::is_ascii_lowercase)
+                .map(|c| (ENGLISH_FREQ[(c as u8 - b'a') as usize] / 100.0).ln())
+                .sum();
+            let word_score = plaintext
+                .split_whitespace()
+                .filter(|w| WORDS.contains(w))
+                .map(|w| w.len() as f64 * WORD_BONUS)
+                .sum::<f64>();
+
+            (letter_score + word_score, shift, plaintext)
+        })
+        .collect();
+
+    let best = scored
+        .iter()
+        .map(|(score, ..)| *score)
+        .fold(f64::MIN, f64::max);
+    let total: f64 = scored.iter().map(|(score, ..)| (score - best).exp()).sum();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    scored
+        .into_iter()
+        .map(|(score, shift, plaintext)| Candidate {
+            shift,
+            plaintext,
+            confidence: (score - best).exp() / total,
+        })
+        .collect()
+}
+
+// Builds a room line that parses into a real room which decrypts back to `plaintext`.
+fn generate_room(plaintext: &str, id: u32) -> String {
+    let dashed = plaintext.chars().map(|c| if c == ' ' { '-' } else { c });
+    let mut line: String = ShiftCipher::LOWERCASE.encrypt(dashed, id).collect();
+
+    let checksum = checksum(&line);
+    line.push('-');
+    line.push_str(&id.to_string());
+    line.push('[');
+    line.extend(checksum);
+    line.push(']');
+
+    line
+}
+
+fn letter_frequencies(name: &str) -> [u32; 26] {
+    let mut letter_freq = [0; 26];
+
+    name.chars()
+        .filter(|c| c.is_ascii_lowercase())
+        .map(|c| c as u8 - b'a')
+        .for_each(|c| letter_freq[c as usize] += 1);
+
+    letter_freq
+}
+
+// Most common first, with ties left in alphabetical order by the stable sort.
+fn ranked_letters(letter_freq: &[u32; 26]) -> [char; 26] {
+    let mut letters: [char; 26] = std::array::from_fn(|i| (b'a' + i as u8) as char);
+
+    letters.sort_by(|a, b| {
+        let a_idx = (*a as u8 - b'a') as usize;
+        let b_idx = (*b as u8 - b'a') as usize;
+        let a_val = letter_freq[a_idx];
+        let b_val = letter_freq[b_idx];
+
+        b_val.cmp(&a_val)
+    });
+
+    letters
+}
+
+fn checksum(name: &str) -> [char; 5] {
+    let letters = ranked_letters(&letter_frequencies(name));
+    [letters[0], letters[1], letters[2], letters[3], letters[4]]
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Tie {
+    count: u32,
+    letters: Vec<char>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ChecksumReport<'a> {
+    room: Room<'a>,
+    expected: [char; 5],
+    // Zero-based index of the first mismatch, which may be past the end of a short checksum.
+    first_difference: Option<usize>,
+    // Groups of equally common letters that reach into the checksum.
+    ties: Vec<Tie>,
+}
+
+impl<'a> ChecksumReport<'a> {
+    fn new(room: Room<'a>) -> Self {
+        let letter_freq = letter_frequencies(room.name);
+        let letters = ranked_letters(&letter_freq);
+        let expected = [letters[0], letters[1], letters[2], letters[3], letters[4]];
+
+        let given: Vec<_> = room.checksum.chars().collect();
+        let first_difference =
+            (0..expected.len().max(given.len())).find(|&i| expected.get(i) != given.get(i));
+
+        let mut ties = Vec::new();
+        let mut start = 0;
+        while start < expected.len() {
+            let count = letter_freq[(letters[start] as u8 - b'a') as usize];
+            let group: Vec<_> = letters[start..]
+                .iter()
+                .take_while(|&&c| letter_freq[(c as u8 - b'a') as usize] == count)
+                .copied()
+                .collect();
+
+            start += group.len();
+            if group.len() > 1 {
+                ties.push(Tie {
+                    count,
+                    letters: group,
+                });
+            }
+        }
+
+        ChecksumReport {
+            room,
+            expected,
+            first_difference,
+            ties,
+        }
+    }
+
+    fn is_real(&self) -> bool {
+        self.first_difference.is_none()
+    }
+}
+
+impl std::fmt::Display for ChecksumReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected: String = self.expected.iter().collect();
+        write!(
+            f,
+            "{}-{}: expected [{}], given [{}]",
+            self.room.name, self.room.id, expected, self.room.checksum
+        )?;
+
+        match self.first_difference {
+            Some(i) => writeln!(f, ", first differs at position {}", i + 1)?,
+            None => writeln!(f, ", real")?,
+        }
+
+        for tie in &self.ties {
+            let letters: Vec<_> = tie.letters.iter().map(char::to_string).collect();
+            writeln!(
+                f,
+                "    tie at {}: {} ordered alphabetically",
+                tie.count,
+                letters.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 struct Room<'a> {
     name: &'a str,
@@ -66,52 +622,106 @@ impl<'a> Room<'a> {
         })
     }
 
-    fn is_real(&self) -> bool {
-        let mut letter_freq = [0; 26];
+    // Like `parse`, but rejects anything that couldn't have come from a well-formed room.
+    // Errors give the 1-based column of the offending character.
+    fn parse_strict(input: &str) -> Result<Room<'_>> {
+        let column = |byte_idx: usize| input[..byte_idx].chars().count() + 1;
 
-        self.name
-            .chars()
-            .filter(|c| c.is_ascii_lowercase())
-            .map(|c| c as u8 - b'a')
-            .for_each(|c| letter_freq[c as usize] += 1);
+        let (left, right) = input
+            .split_once('[')
+            .ok_or_else(|| eyre!("Column {}: expected '['", column(input.len())))?;
+        let (name, id) = left
+            .rsplit_once('-')
+            .ok_or_else(|| eyre!("Column 1: expected a name followed by '-'"))?;
 
-        let mut letters = [
-            'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q',
-            'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-        ];
+        let mut segment_start = 0;
+        for (i, c) in name.char_indices() {
+            match c {
+                'a'..='z' => {}
+                '-' if i == segment_start => {
+                    return Err(eyre!("Column {}: empty name segment", column(i)))
+                }
+                '-' => segment_start = i + 1,
+                'A'..='Z' => {
+                    return Err(eyre!(
+                        "Column {}: uppercase letter {:?} in name",
+                        column(i),
+                        c
+                    ))
+                }
+                '0'..='9' => return Err(eyre!("Column {}: digit {:?} in name", column(i), c)),
+                _ => {
+                    return Err(eyre!(
+                        "Column {}: invalid character {:?} in name",
+                        column(i),
+                        c
+                    ))
+                }
+            }
+        }
+        if segment_start == name.len() {
+            return Err(eyre!("Column {}: empty name segment", column(name.len())));
+        }
 
-        letters.sort_by(|a, b| {
-            let a_idx = (*a as u8 - b'a') as usize;
-            let b_idx = (*b as u8 - b'a') as usize;
-            let a_val = letter_freq[a_idx];
-            let b_val = letter_freq[b_idx];
+        let id_start = name.len() + 1;
+        if let Some((i, c)) = id.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+            return Err(eyre!(
+                "Column {}: invalid sector id character {:?}",
+                column(id_start + i),
+                c
+            ));
+        }
+        let id = id
+            .parse()
+            .map_err(|e| eyre!("Column {}: invalid sector id: {}", column(id_start), e))?;
 
-            b_val.cmp(&a_val)
-        });
+        let checksum_start = left.len() + 1;
+        let (checksum, rest) = right
+            .split_once(']')
+            .ok_or_else(|| eyre!("Column {}: expected ']'", column(input.len())))?;
+        if let Some(c) = rest.chars().next() {
+            let rest_start = checksum_start + checksum.len() + 1;
+            return Err(eyre!(
+                "Column {}: unexpected character {:?} after ']'",
+                column(rest_start),
+                c
+            ));
+        }
+        if let Some((i, c)) = checksum
+            .char_indices()
+            .find(|(_, c)| !c.is_ascii_lowercase())
+        {
+            return Err(eyre!(
+                "Column {}: invalid checksum character {:?}",
+                column(checksum_start + i),
+                c
+            ));
+        }
+        if checksum.len() != 5 {
+            return Err(eyre!(
+                "Column {}: checksum must be exactly five letters, found {}",
+                column(checksum_start),
+                checksum.len()
+            ));
+        }
 
-        self.checksum.chars().eq(letters[..5].iter().copied())
+        Ok(Room { name, id, checksum })
     }
 
-    fn decrypt_name(&self, buf: &mut String) {
-        let letters = [
-            'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q',
-            'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-        ];
+    fn is_real(&self) -> bool {
+        self.checksum.chars().eq(checksum(self.name))
+    }
 
-        buf.extend(self.name.chars().map(|c| match c {
-            '-' => ' ',
-            'a'..='z' => {
-                let c_val = (c as u8 - b'a') as usize + self.id as usize;
-                letters[c_val % 26]
-            }
-            _ => c,
-        }));
+    fn decrypt_name(&self, buf: &mut String) {
+        let spaced = self.name.chars().map(|c| if c == '-' { ' ' } else { c });
+        buf.extend(ShiftCipher::LOWERCASE.decrypt(spaced, self.id));
     }
 }
 
 #[cfg(test)]
 mod tests_1604 {
     use super::*;
+    use crate::rng::Rng;
     use aoc_lib::Example;
 
     #[test]
@@ -165,4 +775,247 @@ mod tests_1604 {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn cipher_test() {
+        let cipher = ShiftCipher::LOWERCASE;
+        let encrypted: String = cipher.encrypt("very encrypted name".chars(), 343).collect();
+        assert_eq!(encrypted, "qzmt zixmtkozy ivhz");
+
+        let decrypted: String = cipher.decrypt(encrypted.chars(), 343).collect();
+        assert_eq!(decrypted, "very encrypted name");
+
+        let digits: Vec<_> = ('0'..='9').collect();
+        let cipher = ShiftCipher::new(&digits).unwrap();
+        let encrypted: String = cipher.encrypt("room-1908".chars(), 13).collect();
+        assert_eq!(encrypted, "room-8675");
+
+        let decrypted: String = cipher.decrypt(encrypted.chars(), 13).collect();
+        assert_eq!(decrypted, "room-1908");
+
+        assert!(ShiftCipher::new(&digits).unwrap().contiguous);
+        let cipher = ShiftCipher::new(&['x', 'a', 'q']).unwrap();
+        assert!(!cipher.contiguous);
+        let encrypted: String = cipher.encrypt("xaqb".chars(), 1).collect();
+        assert_eq!(encrypted, "qxab");
+
+        assert_eq!(ShiftCipher::new(&[]).unwrap_err(), "alphabet is empty");
+        assert_eq!(
+            ShiftCipher::new(&['a', 'b', 'a']).unwrap_err(),
+            "alphabet has a repeated character"
+        );
+    }
+
+    #[test]
+    fn generate_room_test() {
+        let words = [
+            "northpole",
+            "object",
+            "storage",
+            "candy",
+            "cane",
+            "workshop",
+            "reindeer",
+            "sleigh",
+            "radioactive",
+            "egg",
+            "bunny",
+            "chocolate",
+            "basket",
+        ];
+
+        let mut rng = Rng::new(42);
+
+        for _ in 0..500 {
+            let num_words = rng.below(4) + 1;
+            let plaintext = (0..num_words)
+                .map(|_| words[rng.below(words.len() as u64) as usize])
+                .collect::<Vec<_>>()
+                .join(" ");
+            let id = rng.below(1000) as u32 + 100;
+
+            let line = generate_room(&plaintext, id);
+            let room = Room::parse(&line).unwrap();
+            assert!(room.is_real(), "{}", line);
+            assert_eq!(room.id, id);
+
+            let mut decrypted = String::new();
+            room.decrypt_name(&mut decrypted);
+            assert_eq!(decrypted, plaintext, "{}", line);
+        }
+    }
+
+    #[test]
+    fn directory_test() {
+        let lines = [
+            generate_room("northpole object storage", 501),
+            generate_room("candy cane workshop", 242),
+            generate_room("northpole candy storage", 187),
+            "totally-real-room-200[decoy]".to_owned(),
+        ];
+        let rooms: Vec<_> = lines.iter().map(|l| Room::parse(l).unwrap()).collect();
+        let directory = RoomDirectory::new(&rooms);
+
+        let listed: Vec<_> = directory.list().collect();
+        let expected = [
+            ("candy cane workshop", 242),
+            ("northpole candy storage", 187),
+            ("northpole object storage", 501),
+        ];
+        assert_eq!(listed, expected);
+
+        assert_eq!(directory.find_substring("candy"), [242, 187]);
+        assert_eq!(directory.find_substring("pole obj"), [501]);
+        assert_eq!(directory.find_substring("real"), [0; 0]);
+
+        assert_eq!(directory.find_word_prefix("north stor"), [187, 501]);
+        assert_eq!(directory.find_word_prefix("orth"), [0; 0]);
+
+        assert_eq!(directory.find_fuzzy("nortpole objcet"), [501]);
+        assert_eq!(directory.find_fuzzy("candy"), [187, 242]);
+        assert_eq!(directory.find_fuzzy("workshops"), [242]);
+        assert_eq!(directory.find_fuzzy("sleigh"), [0; 0]);
+    }
+
+    #[test]
+    fn checksum_report_test() {
+        let room = Room::parse("totally-real-room-200[decoy]").unwrap();
+        let report = ChecksumReport::new(room);
+        let expected = ChecksumReport {
+            room,
+            expected: ['l', 'o', 'a', 'r', 't'],
+            first_difference: Some(0),
+            ties: vec![
+                Tie {
+                    count: 3,
+                    letters: vec!['l', 'o'],
+                },
+                Tie {
+                    count: 2,
+                    letters: vec!['a', 'r', 't'],
+                },
+            ],
+        };
+        assert_eq!(report, expected);
+        assert_eq!(
+            report.to_string(),
+            "totally-real-room-200: expected [loart], given [decoy], first differs at position 1
+    tie at 3: l, o ordered alphabetically
+    tie at 2: a, r, t ordered alphabetically
+"
+        );
+
+        let cases = [
+            ("aaaaa-bbb-z-y-x-123[abxyz]", None),
+            ("a-b-c-d-e-f-g-h-987[abcde]", None),
+            ("not-a-real-room-404[oarel]", None),
+            ("totally-real-room-200[loarx]", Some(4)),
+            ("totally-real-room-200[loar]", Some(4)),
+            ("totally-real-room-200[loartm]", Some(5)),
+        ];
+        for (line, first_difference) in cases {
+            let room = Room::parse(line).unwrap();
+            let report = ChecksumReport::new(room);
+            assert_eq!(report.first_difference, first_difference, "{}", line);
+            assert_eq!(report.is_real(), room.is_real(), "{}", line);
+        }
+
+        let room = Room::parse("a-b-c-d-e-f-g-h-987[abcde]").unwrap();
+        let ties = ChecksumReport::new(room).ties;
+        assert_eq!(ties.len(), 1);
+        assert_eq!(ties[0].letters, ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h']);
+    }
+
+    #[test]
+    fn crack_test() {
+        let candidates = crack_name("qzmt-zixmtkozy-ivhz");
+        assert_eq!(candidates.len(), 26);
+        assert_eq!(candidates[0].shift, 343 % 26);
+        assert_eq!(candidates[0].plaintext, "very encrypted name");
+        assert!(candidates[0].confidence > 0.5);
+        assert!(candidates
+            .windows(2)
+            .all(|w| w[0].confidence >= w[1].confidence));
+
+        let total: f64 = candidates.iter().map(|c| c.confidence).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        for (plaintext, id) in [
+            ("northpole object storage", 501),
+            ("radioactive chocolate research", 777),
+            ("classified jellybean department", 123),
+        ] {
+            let line = generate_room(plaintext, id);
+            let room = Room::parse(&line).unwrap();
+            let best = &crack_name(room.name)[0];
+            assert_eq!(best.plaintext, plaintext);
+            assert_eq!(best.shift, id % 26);
+        }
+    }
+
+    #[test]
+    fn parse_strict_errors_test() {
+        let cases = [
+            ("aaaaa-bbb-z-y-x-123[abxyz]", None),
+            (
+                "aaaaa-bBb-z-y-x-123[abxyz]",
+                Some("Column 8: uppercase letter 'B' in name"),
+            ),
+            (
+                "aaaaa-bbb-z2-y-x-123[abxyz]",
+                Some("Column 12: digit '2' in name"),
+            ),
+            (
+                "aaaaa--z-y-x-123[abxyz]",
+                Some("Column 7: empty name segment"),
+            ),
+            ("-aaaaa-123[abxyz]", Some("Column 1: empty name segment")),
+            ("aaaaa--123[abxyz]", Some("Column 7: empty name segment")),
+            (
+                "aaaaa-b_b-123[abxyz]",
+                Some("Column 8: invalid character '_' in name"),
+            ),
+            (
+                "aaaaa-bbb-1x3[abxyz]",
+                Some("Column 12: invalid sector id character 'x'"),
+            ),
+            (
+                "aaaaa-bbb-123[abxy]",
+                Some("Column 15: checksum must be exactly five letters, found 4"),
+            ),
+            (
+                "aaaaa-bbb-123[abxyzz]",
+                Some("Column 15: checksum must be exactly five letters, found 6"),
+            ),
+            (
+                "aaaaa-bbb-123[abXyz]",
+                Some("Column 17: invalid checksum character 'X'"),
+            ),
+            ("aaaaa-bbb-123[abxyz", Some("Column 20: expected ']'")),
+            ("aaaaa-bbb-123", Some("Column 14: expected '['")),
+            (
+                "aaaaa-bbb-123[abxyz]x",
+                Some("Column 21: unexpected character 'x' after ']'"),
+            ),
+            (
+                "aaaaa-bbb-123[abxyz]]",
+                Some("Column 21: unexpected character ']' after ']'"),
+            ),
+        ];
+
+        for (line, expected) in cases {
+            match Room::parse_strict(line) {
+                Ok(room) => {
+                    assert_eq!(expected, None, "{}", line);
+                    assert_eq!(room, Room::parse(line).unwrap());
+                }
+                Err(e) => assert_eq!(Some(e.to_string().as_str()), expected, "{}", line),
+            }
+        }
+
+        // The lenient parser still accepts what it did before.
+        let room = Room::parse("aaaaa-bBb-z2-y-x-123[abxy]").unwrap();
+        assert_eq!(room.name, "aaaaa-bBb-z2-y-x");
+        assert_eq!(room.checksum, "abxy");
+    }
 }
@@ -1,60 +1,177 @@
-use aoc_lib::{Bench, BenchResult, Day, NoError};
+use aoc_lib::{Bench, BenchError, BenchResult, Day, NoError};
 
-use std::fmt::Write;
+use crate::{md5_stream::HashStream, rng::Rng};
+use std::{
+    io::{IsTerminal, Write as _},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
 
 pub const DAY: Day = Day {
     day: 5,
     name: "How About a Nice Game of Chass?",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[
+        ("Part 1 Cached", run_part1_cached),
+        ("Part 2 Cached", run_part2_cached),
+        ("Cinematic", run_cinematic),
+    ],
 };
 
+// Set AOC_1605_THREADS to pick how many threads search; otherwise, or if it isn't a number, one
+// per available core.
+fn thread_count() -> usize {
+    std::env::var("AOC_1605_THREADS")
+        .ok()
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
 fn run_part1(input: &str, b: Bench) -> BenchResult {
-    b.bench(|| Ok::<_, NoError>(part1(input)))
+    let threads = thread_count();
+    b.bench(|| Ok::<_, NoError>(part1(HashStream::new(input, 5).with_threads(threads))))
 }
 
 fn run_part2(input: &str, b: Bench) -> BenchResult {
-    b.bench(|| Ok::<_, NoError>(part2(input)))
+    let threads = thread_count();
+    b.bench(|| Ok::<_, NoError>(part2(HashStream::new(input, 5).with_threads(threads))))
+}
+
+fn cache_dir() -> PathBuf {
+    std::env::temp_dir().join("aoc_2016_md5")
 }
 
-fn part1(input: &str) -> String {
-    let mut buf = input.to_owned();
-    let mut password = String::new();
+fn run_part1_cached(input: &str, b: Bench) -> BenchResult {
+    let threads = thread_count();
+    b.bench(|| {
+        let hashes = HashStream::new(input, 5)
+            .with_threads(threads)
+            .with_cache(cache_dir());
+        Ok::<_, NoError>(part1(hashes))
+    })
+}
+
+fn run_part2_cached(input: &str, b: Bench) -> BenchResult {
+    let threads = thread_count();
+    b.bench(|| {
+        let hashes = HashStream::new(input, 5)
+            .with_threads(threads)
+            .with_cache(cache_dir());
+        Ok::<_, NoError>(part2(hashes))
+    })
+}
+
+fn run_cinematic(input: &str, b: Bench) -> BenchResult {
+    let mut stdout = std::io::stdout();
+    let start = Instant::now();
+    let hashes_tried = AtomicUsize::new(0);
+    let hashes = HashStream::new(input, 5)
+        .with_threads(thread_count())
+        .with_progress(&hashes_tried);
+
+    let (password, mut rng) = if stdout.is_terminal() {
+        let mut rng = Rng::new(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64),
+        );
 
-    for i in 0.. {
-        buf.truncate(input.len());
-        write!(&mut buf, "{}", i).unwrap();
+        // The search runs in the background so the unfilled slots and the rate can keep
+        // changing between updates.
+        let password = std::thread::scope(|s| {
+            let (tx, rx) = mpsc::channel();
+            let search = s.spawn(move || {
+                part2_with_progress(hashes, |_, password| {
+                    let _ = tx.send(*password);
+                })
+            });
 
-        let hash = md5::compute(&buf);
+            let mut password = [None; 8];
+            loop {
+                match rx.recv_timeout(Duration::from_millis(50)) {
+                    Ok(update) => password = update,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
 
-        if let [0x00, 0x00, next @ 0x00..=0x0F, ..] = hash.0 {
-            password.push(char::from_digit(next as _, 16).unwrap());
-            if password.len() == 8 {
-                break;
+                let tried = hashes_tried.load(Ordering::Relaxed);
+                let frame = render_frame(&password, &mut rng);
+                print!("\r{}  {}", frame, render_rate(tried, start.elapsed()));
+                stdout.flush()?;
             }
-        }
+            println!();
+
+            Ok::<_, std::io::Error>(search.join().unwrap())
+        })
+        .map_err(|e| BenchError::UserError(e.into()))?;
+
+        (password, rng)
+    } else {
+        // Piped output gets a fixed seed and no rate, so the same input always prints the same
+        // frames.
+        let mut rng = Rng::new(0);
+        let password = part2_with_progress(hashes, |_, password| {
+            println!("{}", render_frame(password, &mut rng));
+        });
+
+        (password, rng)
+    };
+
+    // The search has already run, so only the drawing is timed.
+    let mut solved = [None; 8];
+    for (slot, c) in solved.iter_mut().zip(password.chars()) {
+        *slot = Some(c);
     }
+    b.bench(|| Ok::<_, NoError>(render_frame(&solved, &mut rng)))
+}
+
+fn render_frame(password: &[Option<char>; 8], rng: &mut Rng) -> String {
+    let mut frame = String::from("Decrypting: ");
+    frame.extend(
+        password
+            .iter()
+            .map(|c| c.unwrap_or_else(|| char::from_digit(rng.below(16) as u32, 16).unwrap())),
+    );
 
-    password
+    frame
 }
 
-fn part2(input: &str) -> String {
-    let mut buf = input.to_owned();
-    let mut password = [None::<char>; 8];
-    let mut found = 0;
+fn render_rate(hashes_tried: usize, elapsed: Duration) -> String {
+    let rate = hashes_tried as f64 / elapsed.as_secs_f64().max(1e-9);
+    format!("{:>8.3} MH/s", rate / 1_000_000.0)
+}
 
-    for i in 0.. {
-        buf.truncate(input.len());
-        write!(&mut buf, "{}", i).unwrap();
+fn part1(hashes: impl Iterator<Item = (usize, [u8; 16])>) -> String {
+    hashes
+        .take(8)
+        .map(|(_, hash)| char::from_digit(hash[2] as _, 16).unwrap())
+        .collect()
+}
 
-        let hash = md5::compute(&buf);
+fn part2(hashes: impl Iterator<Item = (usize, [u8; 16])>) -> String {
+    part2_with_progress(hashes, |_, _| {})
+}
+
+// Calls `on_fill` with the hash index and the password so far each time a slot is filled.
+fn part2_with_progress(
+    hashes: impl Iterator<Item = (usize, [u8; 16])>,
+    mut on_fill: impl FnMut(usize, &[Option<char>; 8]),
+) -> String {
+    let mut password = [None::<char>; 8];
+    let mut found = 0;
 
-        match hash.0 {
-            [0x00, 0x00, pos @ 0x00..=0x07, next, ..] if password[pos as usize].is_none() => {
+    for (index, hash) in hashes {
+        match hash {
+            [_, _, pos @ 0x00..=0x07, next, ..] if password[pos as usize].is_none() => {
                 let next = char::from_digit((next >> 4) as _, 16).unwrap();
                 password[pos as usize] = Some(next);
                 found += 1;
+                on_fill(index, &password);
                 if found == 8 {
                     break;
                 }
@@ -70,25 +187,81 @@ fn part2(input: &str) -> String {
 mod tests_1605 {
     use super::*;
 
-    // Commented out tests because they take bloody ages!
-
-    // #[test]
-    #[allow(unused)]
+    // These share the cache used by the cached entries, so only the first run pays for the
+    // search. `md5_stream`'s own tests cover starting without one.
+    #[test]
     fn part1_test() {
         let door_id = "abc";
         let expected = "18f47a30";
-        let actual = part1(door_id);
 
-        assert_eq!(expected, actual);
+        for threads in [1, 3] {
+            let hashes = HashStream::new(door_id, 5)
+                .with_threads(threads)
+                .with_cache(cache_dir());
+            let actual = part1(hashes);
+            assert_eq!(expected, actual, "{} threads", threads);
+        }
     }
 
-    // #[test]
-    #[allow(unused)]
+    #[test]
     fn part2_test() {
         let door_id = "abc";
         let expected = "05ace8e3";
-        let actual = part2(door_id);
 
+        let hashes = HashStream::new(door_id, 5)
+            .with_threads(4)
+            .with_cache(cache_dir());
+        let actual = part2(hashes);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn cinematic_test() {
+        let hash = |pos: u8, next: u8| {
+            let mut hash = [0; 16];
+            hash[2] = pos;
+            hash[3] = next << 4;
+            hash
+        };
+        let hashes = [
+            (100, hash(3, 0xa)),
+            (250, hash(3, 0xb)),
+            (400, hash(0, 0x1)),
+            (700, hash(9, 0x2)),
+            (800, hash(7, 0xf)),
+            (900, hash(1, 0x2)),
+            (1000, hash(2, 0x3)),
+            (1100, hash(4, 0x4)),
+            (1200, hash(5, 0x5)),
+            (1300, hash(6, 0x6)),
+        ];
+
+        let mut rng = Rng::new(0x5EED);
+        let mut frames = Vec::new();
+        let actual = part2_with_progress(hashes.into_iter(), |_, password| {
+            frames.push((*password, render_frame(password, &mut rng)));
+        });
+        assert_eq!(actual, "123a456f");
+        assert_eq!(frames.len(), 8);
+
+        for (password, frame) in &frames {
+            let shown: Vec<_> = frame["Decrypting: ".len()..].chars().take(8).collect();
+            for (slot, shown) in password.iter().zip(&shown) {
+                match slot {
+                    Some(c) => assert_eq!(c, shown, "{}", frame),
+                    None => assert!(shown.is_ascii_hexdigit(), "{}", frame),
+                }
+            }
+        }
+
+        assert_eq!(frames[7].1, "Decrypting: 123a456f");
+
+        // The same seed draws the same frames.
+        let mut rng = Rng::new(0x5EED);
+        for (password, frame) in &frames {
+            assert_eq!(&render_frame(password, &mut rng), frame);
+        }
+
+        assert_eq!(render_rate(101, Duration::from_millis(1)), "   0.101 MH/s");
+    }
 }
@@ -1,13 +1,18 @@
 use aoc_lib::{Bench, BenchResult, Day, NoError, UserError};
 
-use color_eyre::eyre::{eyre, Result};
+use std::fmt::Write;
+
+use color_eyre::{
+    eyre::{eyre, Result},
+    Report,
+};
 
 pub const DAY: Day = Day {
     day: 12,
     name: "Leonardo's Monorail",
     part_1: run_part1,
     part_2: Some(run_part2),
-    other: &[],
+    other: &[("Rewind", run_rewind), ("Coverage", run_coverage)],
 };
 
 fn run_part1(input: &str, b: Bench) -> BenchResult {
@@ -20,7 +25,7 @@ fn run_part1(input: &str, b: Bench) -> BenchResult {
 
     b.bench(|| {
         let mut cpu = Cpu::default();
-        cpu.execute(&instrs);
+        cpu.execute(&instrs, None);
 
         Ok::<_, NoError>(cpu.registers[Register::A as usize])
     })
@@ -37,13 +42,59 @@ fn run_part2(input: &str, b: Bench) -> BenchResult {
     b.bench(|| {
         let mut cpu = Cpu::default();
         cpu.registers[Register::C as usize] = 1;
-        cpu.execute(&instrs);
+        cpu.execute(&instrs, None);
 
         Ok::<_, NoError>(cpu.registers[Register::A as usize])
     })
 }
 
-#[derive(Debug, Clone, Copy)]
+fn run_rewind(input: &str, b: Bench) -> BenchResult {
+    let instrs: Vec<_> = input
+        .lines()
+        .map(str::trim)
+        .map(Instruction::parse)
+        .collect::<Result<_, _>>()
+        .map_err(UserError)?;
+
+    b.bench(|| {
+        let mut recorder = Recorder::new(&instrs, Cpu::default(), 4096);
+        recorder.run();
+        let end = recorder.step();
+
+        let last_write = recorder
+            .reverse_continue(Register::A)
+            .ok_or_else(|| eyre!("Register a was never written"))?;
+        recorder.replay_to(end);
+
+        Ok::<_, Report>(last_write)
+    })
+}
+
+fn run_coverage(input: &str, b: Bench) -> BenchResult {
+    let lines: Vec<_> = input.lines().map(str::trim).collect();
+    let instrs: Vec<_> = lines
+        .iter()
+        .copied()
+        .map(Instruction::parse)
+        .collect::<Result<_, _>>()
+        .map_err(UserError)?;
+
+    b.bench_alt(|| {
+        let mut coverage = Coverage::new(instrs.len());
+
+        for c in [0, 1] {
+            let mut run_coverage = Coverage::new(instrs.len());
+            let mut cpu = Cpu::default();
+            cpu.registers[Register::C as usize] = c;
+            cpu.execute(&instrs, Some(&mut run_coverage));
+            coverage.merge(&run_coverage);
+        }
+
+        Ok::<_, NoError>(coverage.report(&instrs, &lines))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Register {
     A = 0,
     B = 1,
@@ -98,6 +149,14 @@ enum Instruction {
 }
 
 impl Instruction {
+    fn dst(self) -> Option<Register> {
+        match self {
+            Instruction::Copy { dst, .. } => Some(dst),
+            Instruction::Increment(r) | Instruction::Decrement(r) => Some(r),
+            Instruction::JumpNonZero { .. } => None,
+        }
+    }
+
     fn parse(instr: &str) -> Result<Instruction> {
         let mut parts = instr.splitn(3, ' ');
         let op = parts.next();
@@ -119,44 +178,291 @@ impl Instruction {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy)]
 struct Cpu {
     registers: [isize; 4],
 }
 
 impl Cpu {
-    fn execute(&mut self, instrs: &[Instruction]) {
-        let mut pc = 0;
+    fn step(&mut self, instrs: &[Instruction], pc: usize) -> Option<usize> {
+        let next_pc = match *instrs.get(pc)? {
+            Instruction::Copy { src, dst } => {
+                self.registers[dst as usize] = src.get(self);
+                pc + 1
+            }
+            Instruction::Increment(reg) => {
+                self.registers[reg as usize] += 1;
+                pc + 1
+            }
+            Instruction::Decrement(reg) => {
+                self.registers[reg as usize] -= 1;
+                pc + 1
+            }
+            Instruction::JumpNonZero { val, offset } => {
+                let tst_val = val.get(self);
+                if tst_val != 0 {
+                    pc.wrapping_add(offset as usize) // Just let overflow handle the negative case.
+                } else {
+                    pc + 1
+                }
+            }
+        };
+
+        Some(next_pc)
+    }
 
+    fn execute(&mut self, instrs: &[Instruction], mut coverage: Option<&mut Coverage>) {
+        let mut pc = 0;
         loop {
-            let instr = match instrs.get(pc) {
-                Some(i) => *i,
-                None => return,
+            if let Some(coverage) = coverage.as_deref_mut() {
+                coverage.record(self, instrs, pc);
+            }
+
+            match self.step(instrs, pc) {
+                Some(next_pc) => pc = next_pc,
+                None => break,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct BranchCoverage {
+    taken: bool,
+    not_taken: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Coverage {
+    executed: Vec<bool>,
+    branches: Vec<BranchCoverage>,
+}
+
+impl Coverage {
+    fn new(len: usize) -> Self {
+        Self {
+            executed: vec![false; len],
+            branches: vec![BranchCoverage::default(); len],
+        }
+    }
+
+    // Called before the instruction at `pc` runs, as a taken `jnz x 1` lands on the next
+    // instruction anyway, so the direction can't be told from where it goes.
+    fn record(&mut self, cpu: &Cpu, instrs: &[Instruction], pc: usize) {
+        let Some(instr) = instrs.get(pc) else {
+            return;
+        };
+
+        self.executed[pc] = true;
+        if let Instruction::JumpNonZero { val, .. } = instr {
+            if val.get(cpu) != 0 {
+                self.branches[pc].taken = true;
+            } else {
+                self.branches[pc].not_taken = true;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Coverage) {
+        if other.executed.len() > self.executed.len() {
+            self.executed.resize(other.executed.len(), false);
+            self.branches
+                .resize(other.branches.len(), BranchCoverage::default());
+        }
+
+        for (dst, src) in self.executed.iter_mut().zip(&other.executed) {
+            *dst |= *src;
+        }
+
+        for (dst, src) in self.branches.iter_mut().zip(&other.branches) {
+            dst.taken |= src.taken;
+            dst.not_taken |= src.not_taken;
+        }
+    }
+
+    fn report(&self, instrs: &[Instruction], lines: &[&str]) -> String {
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut buf = String::new();
+        let mut num_branches = 0;
+        let mut branch_dirs_hit = 0;
+
+        for (i, ((instr, line), (&executed, branch))) in instrs
+            .iter()
+            .zip(lines)
+            .zip(self.executed.iter().zip(&self.branches))
+            .enumerate()
+        {
+            let is_branch = matches!(instr, Instruction::JumpNonZero { .. });
+            let status = match (executed, is_branch, branch.taken, branch.not_taken) {
+                (false, _, _, _) => "not run",
+                (true, false, _, _) => "run",
+                (true, true, true, true) => "both",
+                (true, true, true, false) => "taken",
+                (true, true, false, true) => "not taken",
+                (true, true, false, false) => unreachable!(),
             };
 
-            match instr {
-                Instruction::Copy { src, dst } => {
-                    self.registers[dst as usize] = src.get(self);
-                    pc += 1;
-                }
-                Instruction::Increment(reg) => {
-                    self.registers[reg as usize] += 1;
-                    pc += 1;
-                }
-                Instruction::Decrement(reg) => {
-                    self.registers[reg as usize] -= 1;
-                    pc += 1;
-                }
-                Instruction::JumpNonZero { val, offset } => {
-                    let tst_val = val.get(self);
-                    if tst_val != 0 {
-                        pc = pc.wrapping_add(offset as usize); // Just let overflow handle the negative case.
-                    } else {
-                        pc += 1;
-                    }
-                }
+            if is_branch {
+                num_branches += 2;
+                branch_dirs_hit += branch.taken as usize + branch.not_taken as usize;
             }
+
+            writeln!(
+                &mut buf,
+                "{:>4} {:<width$}  {}",
+                i,
+                line,
+                status,
+                width = width
+            )
+            .unwrap();
         }
+
+        let num_executed = self.executed.iter().filter(|e| **e).count();
+        writeln!(
+            &mut buf,
+            "Instructions: {}/{}, branch directions: {}/{}",
+            num_executed,
+            self.executed.len(),
+            branch_dirs_hit,
+            num_branches
+        )
+        .unwrap();
+
+        buf
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Delta {
+    pc: usize,
+    // The register written by this step, and the value it held beforehand.
+    write: Option<(Register, isize)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Checkpoint {
+    step: usize,
+    pc: usize,
+    cpu: Cpu,
+}
+
+// Records every step as a register delta so execution can be run backwards. Deltas are
+// only kept back to the most recent checkpoint; stepping back past one replays forward
+// from the checkpoint before it.
+struct Recorder<'a> {
+    instrs: &'a [Instruction],
+    cpu: Cpu,
+    pc: usize,
+    deltas: Vec<Delta>,
+    checkpoints: Vec<Checkpoint>,
+    interval: usize,
+}
+
+impl<'a> Recorder<'a> {
+    fn new(instrs: &'a [Instruction], cpu: Cpu, interval: usize) -> Self {
+        let interval = interval.max(1);
+        Self {
+            instrs,
+            cpu,
+            pc: 0,
+            deltas: Vec::with_capacity(interval),
+            checkpoints: vec![Checkpoint {
+                step: 0,
+                pc: 0,
+                cpu,
+            }],
+            interval,
+        }
+    }
+
+    fn step(&self) -> usize {
+        self.checkpoints.last().map_or(0, |c| c.step) + self.deltas.len()
+    }
+
+    fn step_forward(&mut self) -> bool {
+        let instr = match self.instrs.get(self.pc) {
+            Some(i) => *i,
+            None => return false,
+        };
+
+        if self.deltas.len() == self.interval {
+            self.checkpoints.push(Checkpoint {
+                step: self.step(),
+                pc: self.pc,
+                cpu: self.cpu,
+            });
+            self.deltas.clear();
+        }
+
+        let write = instr
+            .dst()
+            .map(|reg| (reg, self.cpu.registers[reg as usize]));
+        self.deltas.push(Delta { pc: self.pc, write });
+        self.pc = self
+            .cpu
+            .step(self.instrs, self.pc)
+            .expect("pc was checked above");
+
+        true
+    }
+
+    fn run(&mut self) {
+        while self.step_forward() {}
+    }
+
+    fn restore(&mut self, checkpoint: usize) {
+        self.checkpoints.truncate(checkpoint + 1);
+        let Checkpoint { pc, cpu, .. } = self.checkpoints[checkpoint];
+        self.pc = pc;
+        self.cpu = cpu;
+        self.deltas.clear();
+    }
+
+    fn step_back(&mut self) -> Option<Delta> {
+        if self.deltas.is_empty() {
+            // Refill the deltas from the previous checkpoint.
+            if self.checkpoints.len() < 2 {
+                return None;
+            }
+            self.restore(self.checkpoints.len() - 2);
+            for _ in 0..self.interval {
+                self.step_forward();
+            }
+        }
+
+        let delta = self.deltas.pop()?;
+        if let Some((reg, old)) = delta.write {
+            self.cpu.registers[reg as usize] = old;
+        }
+        self.pc = delta.pc;
+
+        Some(delta)
+    }
+
+    // Steps back until the most recent write to `reg` has been undone, and returns the step
+    // number of that write.
+    fn reverse_continue(&mut self, reg: Register) -> Option<usize> {
+        while let Some(delta) = self.step_back() {
+            if matches!(delta.write, Some((r, _)) if r == reg) {
+                return Some(self.step());
+            }
+        }
+
+        None
+    }
+
+    fn replay_to(&mut self, target: usize) {
+        if target < self.step() {
+            let checkpoint = self
+                .checkpoints
+                .iter()
+                .rposition(|c| c.step <= target)
+                .unwrap_or(0);
+            self.restore(checkpoint);
+        }
+
+        while self.step() < target && self.step_forward() {}
     }
 }
 
@@ -181,8 +487,98 @@ mod tests_1612 {
             .unwrap();
 
         let mut cpu = Cpu::default();
-        cpu.execute(&instrs);
+        cpu.execute(&instrs, None);
 
         assert_eq!(cpu.registers[Register::A as usize], 42);
     }
+
+    #[test]
+    fn rewind() {
+        let instrs: Vec<_> = "cpy 41 a\ninc a\ninc a\ndec a\njnz a 2\ndec a"
+            .lines()
+            .map(Instruction::parse)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let mut recorder = Recorder::new(&instrs, Cpu::default(), 2);
+        recorder.run();
+        assert_eq!(recorder.step(), 5);
+        assert_eq!(recorder.cpu.registers[Register::A as usize], 42);
+
+        recorder.step_back().unwrap();
+        assert_eq!(recorder.step(), 4);
+        assert_eq!(recorder.pc, 4);
+
+        assert_eq!(recorder.reverse_continue(Register::A), Some(3));
+        assert_eq!(recorder.cpu.registers[Register::A as usize], 43);
+
+        assert_eq!(recorder.reverse_continue(Register::B), None);
+        assert_eq!(recorder.step(), 0);
+        assert_eq!(recorder.cpu.registers[Register::A as usize], 0);
+
+        recorder.replay_to(5);
+        assert_eq!(recorder.cpu.registers[Register::A as usize], 42);
+
+        recorder.replay_to(1);
+        assert_eq!(recorder.cpu.registers[Register::A as usize], 41);
+    }
+
+    #[test]
+    fn coverage() {
+        let instrs: Vec<_> = "jnz c 2\ninc a\njnz 0 5\ninc b"
+            .lines()
+            .map(Instruction::parse)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let mut first = Coverage::new(instrs.len());
+        Cpu::default().execute(&instrs, Some(&mut first));
+        assert_eq!(first.executed, [true, true, true, true]);
+        assert_eq!(
+            first.branches[0],
+            BranchCoverage {
+                taken: false,
+                not_taken: true
+            }
+        );
+
+        let mut second = Coverage::new(instrs.len());
+        let mut cpu = Cpu::default();
+        cpu.registers[Register::C as usize] = 1;
+        cpu.execute(&instrs, Some(&mut second));
+        assert_eq!(second.executed, [true, false, true, true]);
+
+        first.merge(&second);
+        assert_eq!(
+            first.branches[0],
+            BranchCoverage {
+                taken: true,
+                not_taken: true
+            }
+        );
+        assert_eq!(
+            first.branches[2],
+            BranchCoverage {
+                taken: false,
+                not_taken: true
+            }
+        );
+
+        let instrs: Vec<_> = "cpy 1 a\njnz a 1\ninc b"
+            .lines()
+            .map(Instruction::parse)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let mut coverage = Coverage::new(instrs.len());
+        Cpu::default().execute(&instrs, Some(&mut coverage));
+        assert_eq!(coverage.executed, [true, true, true]);
+        assert_eq!(
+            coverage.branches[1],
+            BranchCoverage {
+                taken: true,
+                not_taken: false
+            }
+        );
+    }
 }
@@ -2,6 +2,8 @@ use aoc_lib::TracingAlloc;
 use color_eyre::Result;
 
 mod days;
+mod md5_stream;
+mod rng;
 
 #[global_allocator]
 static ALLOC: TracingAlloc = TracingAlloc;
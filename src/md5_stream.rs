@@ -0,0 +1,280 @@
+use color_eyre::eyre::Result;
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const CHUNK_SIZE: usize = 16_384;
+
+// Yields `(index, digest)` for every `md5(prefix + index)` that starts with the given number of
+// zero nibbles, in index order.
+#[derive(Debug)]
+pub struct HashStream<'a> {
+    prefix: &'a str,
+    zero_nibbles: usize,
+    threads: usize,
+    next_index: usize,
+    pending: VecDeque<usize>,
+    cache: Option<HashCache>,
+    progress: Option<&'a AtomicUsize>,
+}
+
+impl<'a> HashStream<'a> {
+    pub fn new(prefix: &'a str, zero_nibbles: usize) -> Self {
+        Self {
+            prefix,
+            zero_nibbles: zero_nibbles.min(32),
+            threads: 1,
+            next_index: 0,
+            pending: VecDeque::new(),
+            cache: None,
+            progress: None,
+        }
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    // Indices found on previous runs are read from a file in `dir`, and new ones are written
+    // back as the search goes. Failing to write only costs time on the next run, so write
+    // errors are ignored.
+    pub fn with_cache(self, dir: impl AsRef<Path>) -> Self {
+        let cache = HashCache::load(dir.as_ref(), self.prefix, self.zero_nibbles);
+
+        Self {
+            next_index: cache.searched,
+            pending: cache.found.iter().copied().collect(),
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    // After each round of searching, `progress` is set to the index searched up to, so another
+    // thread can watch how far the search has got.
+    pub fn with_progress(self, progress: &'a AtomicUsize) -> Self {
+        Self {
+            progress: Some(progress),
+            ..self
+        }
+    }
+
+    fn is_interesting(&self, hash: &[u8; 16]) -> bool {
+        let full_bytes = self.zero_nibbles / 2;
+        hash[..full_bytes].iter().all(|&b| b == 0)
+            && (self.zero_nibbles.is_multiple_of(2) || hash[full_bytes] <= 0x0F)
+    }
+
+    fn hash(&self, index: usize) -> [u8; 16] {
+        md5::compute(format!("{}{}", self.prefix, index)).0
+    }
+
+    fn hash_chunk(&self, range: Range<usize>) -> Vec<usize> {
+        let mut buf = self.prefix.to_owned();
+
+        range
+            .filter(|i| {
+                buf.truncate(self.prefix.len());
+                write!(&mut buf, "{}", i).unwrap();
+
+                self.is_interesting(&md5::compute(&buf).0)
+            })
+            .collect()
+    }
+
+    // Hands one chunk to each thread, and queues what they find in index order.
+    fn search_round(&mut self) {
+        let round_start = self.next_index;
+        let this = &*self;
+        let chunks: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..this.threads)
+                .map(|t| {
+                    let start = round_start + t * CHUNK_SIZE;
+                    s.spawn(move || this.hash_chunk(start..start + CHUNK_SIZE))
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        self.next_index += self.threads * CHUNK_SIZE;
+        if let Some(progress) = self.progress {
+            progress.store(self.next_index, Ordering::Relaxed);
+        }
+        let found: Vec<_> = chunks.into_iter().flatten().collect();
+        self.pending.extend(&found);
+
+        if let Some(cache) = &mut self.cache {
+            cache.found.extend(found);
+            cache.searched = self.next_index;
+            let _ = cache.save();
+        }
+    }
+}
+
+impl Iterator for HashStream<'_> {
+    type Item = (usize, [u8; 16]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            self.search_round();
+        }
+
+        let index = self.pending.pop_front()?;
+        Some((index, self.hash(index)))
+    }
+}
+
+#[derive(Debug)]
+struct HashCache {
+    path: PathBuf,
+    // Every index below this has been checked.
+    searched: usize,
+    found: Vec<usize>,
+}
+
+impl HashCache {
+    // A file that can't be read or doesn't make sense is ignored, and replaced on the next save.
+    fn load(dir: &Path, prefix: &str, zero_nibbles: usize) -> Self {
+        // The prefix is hex encoded so that any prefix makes a valid file name.
+        let mut name = String::from("md5_");
+        for b in prefix.bytes() {
+            write!(name, "{:02x}", b).unwrap();
+        }
+        write!(name, "_{}.txt", zero_nibbles).unwrap();
+        let path = dir.join(name);
+
+        let (searched, found) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| Self::parse(&contents))
+            .unwrap_or_default();
+
+        HashCache {
+            path,
+            searched,
+            found,
+        }
+    }
+
+    fn parse(contents: &str) -> Option<(usize, Vec<usize>)> {
+        let mut lines = contents.lines();
+        let searched = lines.next()?.strip_prefix("searched ")?.parse().ok()?;
+        let found: Vec<usize> = lines.map(str::parse).collect::<Result<_, _>>().ok()?;
+
+        let in_order = found.windows(2).all(|w| w[0] < w[1]);
+        let in_range = found.last().is_none_or(|&i| i < searched);
+        (in_order && in_range).then_some((searched, found))
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut contents = format!("searched {}\n", self.searched);
+        for i in &self.found {
+            writeln!(contents, "{}", i)?;
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Written to the side and renamed so an interrupted run can't leave a truncated file.
+        // Each save gets its own temporary file, as several streams may share a cache.
+        static SAVES: AtomicUsize = AtomicUsize::new(0);
+        let save = SAVES.fetch_add(1, Ordering::Relaxed);
+        let tmp = self
+            .path
+            .with_extension(format!("{}_{}.tmp", std::process::id(), save));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_nibbles() {
+        let stream = HashStream::new("abc", 5);
+        assert!(
+            stream.is_interesting(&[0x00, 0x00, 0x08, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert!(!stream.is_interesting(&[0x00, 0x00, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+
+        let stream = HashStream::new("abc", 2);
+        assert!(stream.is_interesting(&[0x00, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(!stream.is_interesting(&[0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+
+        let expected: Vec<_> = (0..)
+            .map(|i: usize| (i, md5::compute(format!("abc{}", i)).0))
+            .filter(|(_, h)| h[0] == 0 && h[1] <= 0x0F)
+            .take(30)
+            .collect();
+
+        let single: Vec<_> = HashStream::new("abc", 3).take(30).collect();
+        let threaded: Vec<_> = HashStream::new("abc", 3).with_threads(3).take(30).collect();
+        assert_eq!(single, expected);
+        assert_eq!(threaded, expected);
+    }
+
+    #[test]
+    fn progress() {
+        let progress = AtomicUsize::new(0);
+        let mut stream = HashStream::new("abc", 3)
+            .with_threads(2)
+            .with_progress(&progress);
+        assert_eq!(progress.load(Ordering::Relaxed), 0);
+
+        let (index, _) = stream.next().unwrap();
+        let searched = progress.load(Ordering::Relaxed);
+        assert_eq!(searched % (2 * CHUNK_SIZE), 0);
+        assert!(searched > index);
+    }
+
+    #[test]
+    fn cache() {
+        let dir = std::env::temp_dir().join(format!("aoc_2016_md5_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let expected: Vec<_> = HashStream::new("cache-test", 4).take(5).collect();
+
+        let first: Vec<_> = HashStream::new("cache-test", 4)
+            .with_cache(&dir)
+            .take(5)
+            .collect();
+        assert_eq!(first, expected);
+
+        let cache = HashCache::load(&dir, "cache-test", 4);
+        assert!(cache.searched > expected[4].0);
+        assert!(cache.found.starts_with(&[expected[0].0, expected[1].0]));
+
+        // A second run starts with the cached indices, then carries on searching past them.
+        let expected: Vec<_> = HashStream::new("cache-test", 4)
+            .take(cache.found.len() + 3)
+            .collect();
+        let second: Vec<_> = HashStream::new("cache-test", 4)
+            .with_cache(&dir)
+            .take(expected.len())
+            .collect();
+        assert_eq!(second, expected);
+
+        // Unreadable or inconsistent files are thrown away and the search starts over.
+        let expected: Vec<_> = HashStream::new("a", 1).take(3).collect();
+        for contents in ["nonsense", "searched 5\n2\n9\n", "searched 50\n9\n2\n"] {
+            fs::write(dir.join("md5_61_1.txt"), contents).unwrap();
+            let actual: Vec<_> = HashStream::new("a", 1).with_cache(&dir).take(3).collect();
+            assert_eq!(actual, expected, "{:?}", contents);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+// SplitMix64. Small, and stable for a given seed, which is all that generated test inputs and
+// the Day 5 display need.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // In `0..max`. The modulo bias doesn't matter for our uses.
+    pub fn below(&mut self, max: u64) -> u64 {
+        self.next_u64() % max
+    }
+}